@@ -0,0 +1,60 @@
+//! Winner determination over synthetic bid books.
+//!
+//! The exact search is benchmarked up to the default `exact_search_limit`, the greedy
+//! approximation on larger books with a fixed number of improvement rounds, so every iteration
+//! does the same amount of work instead of running until the time budget is spent.
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use marketplace_solver::{
+    auction::{compute_auction_results, replay_auction, WinnerDetermination},
+    workload::{Workload, WorkloadOptions},
+    AuctionOptions,
+};
+
+const GREEDY_ROUNDS: u64 = 10;
+
+fn workload(bids: usize) -> Workload {
+    Workload::new(WorkloadOptions {
+        builders: bids,
+        namespaces: 10,
+        bids_per_builder: 1,
+        ..Default::default()
+    })
+}
+
+fn exact(c: &mut Criterion) {
+    let options = AuctionOptions::default();
+    let mut group = c.benchmark_group("exact");
+
+    for bids in [5, 10, 15, 20] {
+        let inputs = workload(bids).auction_inputs(1);
+        group.bench_with_input(BenchmarkId::from_parameter(bids), &inputs, |b, inputs| {
+            b.iter_batched(
+                || inputs.clone(),
+                |inputs| compute_auction_results(inputs, &options),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+fn greedy(c: &mut Criterion) {
+    let method = WinnerDetermination::Greedy {
+        rounds: GREEDY_ROUNDS,
+    };
+    let mut group = c.benchmark_group("greedy");
+    group.sample_size(10);
+
+    for bids in [100, 1000, 10000] {
+        let inputs = workload(bids).auction_inputs(1);
+        group.bench_with_input(BenchmarkId::from_parameter(bids), &inputs, |b, inputs| {
+            b.iter(|| replay_auction(inputs, method))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, exact, greedy);
+criterion_main!(benches);
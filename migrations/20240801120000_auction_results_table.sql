@@ -0,0 +1,5 @@
+CREATE TABLE auction_results (
+    view_number BIGINT PRIMARY KEY,
+    inputs_commitment TEXT NOT NULL,
+    data JSONB NOT NULL
+);
@@ -0,0 +1,17 @@
+CREATE TABLE builder_bans (
+    account TEXT PRIMARY KEY
+);
+
+CREATE TABLE admin_audit_log (
+    id BIGSERIAL PRIMARY KEY,
+    operator TEXT NOT NULL,
+    request_commitment TEXT NOT NULL UNIQUE,
+    action JSONB NOT NULL,
+    error TEXT,
+    executed_at BIGINT NOT NULL
+);
+
+-- Holds a row while bidding is paused
+CREATE TABLE bidding_paused (
+    paused BOOLEAN PRIMARY KEY CHECK (paused)
+);
@@ -0,0 +1,8 @@
+CREATE TABLE rollup_registration_changes (
+    cursor BIGSERIAL PRIMARY KEY,
+    namespace_id BIGINT NOT NULL,
+    kind TEXT NOT NULL,
+    registration JSONB,
+    view_number BIGINT,
+    changed_at BIGINT NOT NULL
+);
@@ -0,0 +1,6 @@
+CREATE TABLE bids (
+    view_number BIGINT NOT NULL,
+    commitment TEXT NOT NULL,
+    data JSONB NOT NULL,
+    PRIMARY KEY (view_number, commitment)
+);
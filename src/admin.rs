@@ -0,0 +1,266 @@
+use std::{
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use committable::{Commitment, Committable, RawCommitmentBuilder};
+use espresso_types::{v0_3::RollupRegistration, FeeAccount, NamespaceId, SeqTypes};
+use futures::FutureExt;
+use hotshot::types::{BLSPubKey, SignatureKey};
+use hotshot_types::{signature_key::BLSPrivKey, traits::node_implementation::NodeType};
+use serde::{Deserialize, Serialize};
+use tide_disco::{api::ApiError, method::ReadState, Api};
+use vbs::version::StaticVersionType;
+
+use crate::{load_api, signing::SolverSignature, AdminOptions, SolverError, SolverResult};
+
+/// How far the issue time of an admin request may be from the solver's clock, in seconds
+pub const ADMIN_REQUEST_MAX_AGE: u64 = 300;
+
+/// Operations only operators may perform.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AdminAction {
+    /// Replaces a rollup registration without requiring the rollup's signature
+    UpdateRollup(RollupRegistration),
+    DeleteRollup(NamespaceId),
+    BanBuilder(FeeAccount),
+    UnbanBuilder(FeeAccount),
+    /// Recomputes the finalized auction for a view from its persisted bids and the current
+    /// rollup registrations
+    RerunAuction(u64),
+    PauseBidding,
+    ResumeBidding,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdminRequestBody {
+    pub action: AdminAction,
+    /// Unix time the request was created at, in seconds
+    pub issued_at: u64,
+}
+
+impl Committable for AdminRequestBody {
+    fn commit(&self) -> Commitment<Self> {
+        RawCommitmentBuilder::new(&Self::tag())
+            .var_size_field(
+                "action",
+                &bincode::serialize(&self.action).expect("serializable action"),
+            )
+            .u64_field("issued_at", self.issued_at)
+            .finalize()
+    }
+
+    fn tag() -> String {
+        "ADMIN_REQUEST".to_string()
+    }
+}
+
+impl AdminRequestBody {
+    pub fn new(action: AdminAction) -> Self {
+        Self {
+            action,
+            issued_at: unix_time(),
+        }
+    }
+
+    pub fn signed(self, private_key: &BLSPrivKey) -> SolverResult<AdminRequest> {
+        let signature =
+            <SeqTypes as NodeType>::SignatureKey::sign(private_key, self.commit().as_ref())
+                .map_err(|err| SolverError::InvalidSignature(err.to_string()))?;
+
+        Ok(AdminRequest {
+            body: self,
+            operator: BLSPubKey::from_private(private_key),
+            signature,
+        })
+    }
+}
+
+/// An admin action signed by an operator.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdminRequest {
+    pub body: AdminRequestBody,
+    pub operator: BLSPubKey,
+    pub signature: SolverSignature,
+}
+
+impl AdminRequest {
+    /// Checks that the request is recent and signed by one of `operator_keys`.
+    pub fn authorize(&self, operator_keys: &[BLSPubKey]) -> SolverResult<()> {
+        authorize_operator(
+            operator_keys,
+            &self.operator,
+            &self.signature,
+            self.body.commit(),
+            self.body.issued_at,
+        )
+    }
+}
+
+/// A request to read the audit log, which names the solver's operators and what they did.
+///
+/// Unlike an [`AdminRequest`], it does not change anything, so it can be sent again until it
+/// expires.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditLogQuery {
+    /// Unix time the query was created at, in seconds
+    pub issued_at: u64,
+}
+
+impl Committable for AuditLogQuery {
+    fn commit(&self) -> Commitment<Self> {
+        RawCommitmentBuilder::new(&Self::tag())
+            .u64_field("issued_at", self.issued_at)
+            .finalize()
+    }
+
+    fn tag() -> String {
+        "AUDIT_LOG_QUERY".to_string()
+    }
+}
+
+impl AuditLogQuery {
+    pub fn new() -> Self {
+        Self {
+            issued_at: unix_time(),
+        }
+    }
+
+    pub fn signed(self, private_key: &BLSPrivKey) -> SolverResult<SignedAuditLogQuery> {
+        let signature =
+            <SeqTypes as NodeType>::SignatureKey::sign(private_key, self.commit().as_ref())
+                .map_err(|err| SolverError::InvalidSignature(err.to_string()))?;
+
+        Ok(SignedAuditLogQuery {
+            body: self,
+            operator: BLSPubKey::from_private(private_key),
+            signature,
+        })
+    }
+}
+
+impl Default for AuditLogQuery {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An audit log query signed by an operator.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedAuditLogQuery {
+    pub body: AuditLogQuery,
+    pub operator: BLSPubKey,
+    pub signature: SolverSignature,
+}
+
+impl SignedAuditLogQuery {
+    /// Checks that the query is recent and signed by one of `operator_keys`.
+    pub fn authorize(&self, operator_keys: &[BLSPubKey]) -> SolverResult<()> {
+        authorize_operator(
+            operator_keys,
+            &self.operator,
+            &self.signature,
+            self.body.commit(),
+            self.body.issued_at,
+        )
+    }
+}
+
+fn authorize_operator<T: Committable>(
+    operator_keys: &[BLSPubKey],
+    operator: &BLSPubKey,
+    signature: &SolverSignature,
+    commitment: Commitment<T>,
+    issued_at: u64,
+) -> SolverResult<()> {
+    if !operator_keys.contains(operator) {
+        return Err(SolverError::Unauthorized(format!(
+            "{operator} is not an operator key"
+        )));
+    }
+
+    if !<SeqTypes as NodeType>::SignatureKey::validate(operator, signature, commitment.as_ref()) {
+        return Err(SolverError::Unauthorized("invalid signature".to_string()));
+    }
+
+    if unix_time().abs_diff(issued_at) > ADMIN_REQUEST_MAX_AGE {
+        return Err(SolverError::Unauthorized(format!(
+            "request issued at {issued_at} has expired"
+        )));
+    }
+
+    Ok(())
+}
+
+/// An executed admin action.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditLogEntry {
+    pub id: i64,
+    pub operator: String,
+    /// Commitment of the signed request, which can be executed only once
+    pub request_commitment: String,
+    pub action: AdminAction,
+    /// Why the action failed, if it did
+    pub error: Option<String>,
+    /// Unix time the action was executed at, in seconds
+    pub executed_at: i64,
+}
+
+#[async_trait]
+pub trait SolverAdmin {
+    /// Executes an authorized admin request and records it in the audit log.
+    async fn execute_admin_request(&self, request: AdminRequest) -> SolverResult<AuditLogEntry>;
+    async fn get_audit_log(&self) -> SolverResult<Vec<AuditLogEntry>>;
+}
+
+pub fn define_admin_api<State, VERSION>(
+    options: AdminOptions,
+) -> Result<Api<State, SolverError, VERSION>, ApiError>
+where
+    VERSION: StaticVersionType + 'static,
+    State: 'static + Send + Sync + ReadState,
+    <State as ReadState>::State: Send + Sync + SolverAdmin,
+{
+    let mut api = load_api::<State, SolverError, VERSION>(
+        None::<&str>,
+        include_str!("../api/admin.toml"),
+        Vec::new(),
+    )?;
+
+    let operator_keys = Arc::new(options.operator_keys);
+
+    let audit_log_keys = operator_keys.clone();
+
+    api.at("action", move |req, state| {
+        let operator_keys = operator_keys.clone();
+        async move {
+            let request = req.body_json::<AdminRequest>()?;
+            request.authorize(&operator_keys)?;
+            state
+                .read(|state| async move { state.execute_admin_request(request).await }.boxed())
+                .await
+        }
+        .boxed()
+    })?
+    .at("audit_log", move |req, state| {
+        let operator_keys = audit_log_keys.clone();
+        async move {
+            let query = req.body_json::<SignedAuditLogQuery>()?;
+            query.authorize(&operator_keys)?;
+            state
+                .read(|state| async move { state.get_audit_log().await }.boxed())
+                .await
+        }
+        .boxed()
+    })?;
+
+    Ok(api)
+}
+
+pub(crate) fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time after unix epoch")
+        .as_secs()
+}
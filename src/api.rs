@@ -1,24 +1,31 @@
 use std::{
     fs,
     path::{Path, PathBuf},
+    sync::Arc,
 };
 
+use committable::Committable;
 use espresso_types::{
-    v0_3::{RollupRegistration, RollupUpdate},
-    NamespaceId,
+    v0_3::{BidTx, RollupRegistration, RollupUpdate},
+    FeeAccount, NamespaceId, PubKey,
+};
+use futures::{FutureExt, StreamExt, TryFutureExt};
+use hotshot_types::{
+    data::ViewNumber,
+    traits::{node_implementation::ConsensusTime, signature_key::SignatureKey},
 };
-use futures::FutureExt;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use tide_disco::{
-    api::ApiError,
-    method::{ReadState, WriteState},
-    Api, RequestError, StatusCode,
-};
+use tide_disco::{api::ApiError, method::ReadState, Api, RequestError, StatusCode};
 use toml::{map::Entry, Value};
 use vbs::version::StaticVersionType;
 
-use crate::state::UpdateSolverState;
+use crate::{
+    builders::BuilderRegistration,
+    limits::RequestLimits,
+    state::{ResultsRequest, UpdateSolverState},
+    RateLimitOptions,
+};
 
 #[derive(Debug, Error, Serialize, Deserialize)]
 pub enum SolverError {
@@ -36,6 +43,46 @@ pub enum SolverError {
     Database(String),
     #[error("serde json err: {0}")]
     SerdeJsonError(String),
+    #[error("auction for view {0} is not finalized")]
+    AuctionNotFinalized(u64),
+    #[error("invalid bid signature: {0}")]
+    InvalidBidSignature(String),
+    #[error("bid does not reference any namespace")]
+    EmptyBidNamespaces,
+    #[error("namespace {0} is referenced more than once in the bid")]
+    DuplicateBidNamespace(NamespaceId),
+    #[error("namespace {0} is not registered")]
+    UnregisteredNamespace(NamespaceId),
+    #[error("namespace {0} is not active")]
+    InactiveNamespace(NamespaceId),
+    #[error("bid view {view} is outside of the open views {first}..={last}")]
+    BidViewOutOfRange { view: u64, first: u64, last: u64 },
+    #[error("bid amount must be non-zero")]
+    ZeroBidAmount,
+    #[error("bid amount {amount} exceeds the maximum of {max}")]
+    BidAmountTooLarge { amount: String, max: String },
+    #[error("invalid builder url: {0}")]
+    InvalidBuilderUrl(String),
+    #[error("solver is shutting down")]
+    ShuttingDown,
+    #[error("rate limit exceeded for {0}")]
+    RateLimited(String),
+    #[error("request body of {size} bytes exceeds the maximum of {max} bytes")]
+    RequestTooLarge { size: usize, max: usize },
+    #[error("builder already submitted the maximum of {max} bids for view {view}")]
+    TooManyBids { view: u64, max: usize },
+    #[error("builder {0} is not registered")]
+    UnregisteredBuilder(FeeAccount),
+    #[error("builder {0} is not allowed to bid")]
+    BuilderNotAllowed(FeeAccount),
+    #[error("unauthorized: {0}")]
+    Unauthorized(String),
+    #[error("admin request was already executed")]
+    AdminRequestReplayed,
+    #[error("bidding is paused by the operator")]
+    BiddingPaused,
+    #[error("rollup {0} is not registered")]
+    RollupNotFound(NamespaceId),
     #[error("request error: {0}")]
     Request(#[from] RequestError),
     #[error("err {status:?} : {message:?}")]
@@ -67,6 +114,16 @@ impl tide_disco::Error for SolverError {
     fn status(&self) -> StatusCode {
         match self {
             Self::Custom { status, .. } => *status,
+            Self::AuctionNotFinalized(_)
+            | Self::UnregisteredBuilder(_)
+            | Self::RollupNotFound(_) => StatusCode::NOT_FOUND,
+            Self::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            Self::AdminRequestReplayed => StatusCode::CONFLICT,
+            Self::BiddingPaused => StatusCode::SERVICE_UNAVAILABLE,
+            Self::BuilderNotAllowed(_) => StatusCode::FORBIDDEN,
+            Self::ShuttingDown => StatusCode::SERVICE_UNAVAILABLE,
+            Self::RateLimited(_) | Self::TooManyBids { .. } => StatusCode::TOO_MANY_REQUESTS,
+            Self::RequestTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
             _ => StatusCode::BAD_REQUEST,
         }
     }
@@ -76,6 +133,9 @@ impl tide_disco::Error for SolverError {
 pub struct ApiOptions {
     pub api_path: Option<PathBuf>,
 
+    /// Rate and size limits of the routes that accept submissions.
+    pub limits: RateLimitOptions,
+
     /// Additional API specification files to merge with `solver-api-path`.
     ///
     /// These optional files may contain route definitions for application-specific routes that have
@@ -83,12 +143,31 @@ pub struct ApiOptions {
     pub extensions: Vec<toml::Value>,
 }
 
+impl ApiOptions {
+    /// Reads the API specification and extensions from TOML files.
+    pub fn from_files(
+        api_path: Option<PathBuf>,
+        extensions: impl IntoIterator<Item = impl AsRef<Path>>,
+    ) -> Result<Self, ApiError> {
+        let extensions = extensions
+            .into_iter()
+            .map(|path| load_toml(path.as_ref()))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            api_path,
+            extensions,
+            ..Default::default()
+        })
+    }
+}
+
 pub fn define_api<State, VERSION>(
     options: ApiOptions,
 ) -> Result<Api<State, SolverError, VERSION>, ApiError>
 where
     VERSION: StaticVersionType + 'static,
-    State: 'static + Send + Sync + ReadState + WriteState,
+    State: 'static + Send + Sync + ReadState,
     <State as ReadState>::State: Send + Sync + UpdateSolverState,
 {
     let mut api = load_api::<State, SolverError, VERSION>(
@@ -97,36 +176,180 @@ where
         options.extensions.clone(),
     )?;
 
-    // TODO ED: We need to fill these in with the appropriate logic later
-    api.post("submit_bid", |_req, _state| {
-        async move { Ok("Bid Submitted") }.boxed()
+    let limits = Arc::new(RequestLimits::new(&options.limits));
+
+    // POST routes are registered with `at` rather than `post`, which would take the state for
+    // writing and so serialize every request behind the slowest one. The state synchronizes
+    // concurrent writes itself.
+    api.at("submit_bid", {
+        let limits = limits.clone();
+        move |req, state| {
+            let limits = limits.clone();
+            async move {
+                limits.check_request(&req)?;
+                let bid = req.body_json::<BidTx>()?;
+                // Forged bids are left for the state to reject without touching the bucket of the
+                // account they name
+                if bid.verify().is_ok() {
+                    limits.check_key(bid.account())?;
+                }
+                state
+                    .read(|state| async move { state.submit_bid_tx(bid).await }.boxed())
+                    .await
+            }
+            .boxed()
+        }
     })?
-    .get("auction_results", |_req, _state| {
-        async move { Ok("Auction Results Gotten") }.boxed()
+    .get("auction_results", |req, state| {
+        async move {
+            let view_number = ViewNumber::new(req.integer_param::<_, u64>("view_number")?);
+            state
+                .calculate_auction_results_permissionless(view_number)
+                .await
+        }
+        .boxed()
     })?
-    .get("auction_results_permissioned", |_req, _state| {
-        async move { Ok("Permissioned Auction Results Gotten") }.boxed()
+    .get("auction_outcome", |req, state| {
+        async move {
+            let view_number = ViewNumber::new(req.integer_param::<_, u64>("view_number")?);
+            state.get_auction_outcome(view_number).await
+        }
+        .boxed()
     })?
-    .post("register_rollup", |req, state| {
+    .at("auction_results_permissioned", |req, state| {
         async move {
-            let body = req.body_json::<RollupRegistration>()?;
-            state.register_rollup(body).await
+            let request = req.body_json::<ResultsRequest>()?;
+            state
+                .read(|state| {
+                    async move { state.calculate_auction_results_permissioned(request).await }
+                        .boxed()
+                })
+                .await
         }
         .boxed()
     })?
-    .post("update_rollup", |req, state| {
+    .stream("auction_results_stream", |req, state| {
         async move {
-            let body = req.body_json::<RollupUpdate>()?;
-            state.update_rollup_registration(body).await
+            let include_bid_counts = req
+                .opt_boolean_param("include_bid_counts")?
+                .unwrap_or(false);
+            let auctions = state
+                .read(|state| async move { state.subscribe_finalized_auctions() }.boxed())
+                .await;
+
+            Ok(auctions.map(move |mut auction| {
+                if !include_bid_counts {
+                    auction.bid_count = None;
+                }
+                Ok(auction)
+            }))
         }
+        .try_flatten_stream()
         .boxed()
     })?
+    .get("solver_key", |_req, state| {
+        async move { Ok(state.solver_key()) }.boxed()
+    })?
+    .at("register_rollup", {
+        let limits = limits.clone();
+        move |req, state| {
+            let limits = limits.clone();
+            async move {
+                limits.check_request(&req)?;
+                let body = req.body_json::<RollupRegistration>()?;
+                if signed_by(&body.body.signature_key, &body.signature, &body.body) {
+                    limits.check_key(body.body.signature_key)?;
+                }
+                state
+                    .read(|state| async move { state.register_rollup(body).await }.boxed())
+                    .await
+            }
+            .boxed()
+        }
+    })?
+    .at("update_rollup", {
+        let limits = limits.clone();
+        move |req, state| {
+            let limits = limits.clone();
+            async move {
+                limits.check_request(&req)?;
+                let body = req.body_json::<RollupUpdate>()?;
+                if signed_by(&body.body.signature_key, &body.signature, &body.body) {
+                    limits.check_key(body.body.signature_key)?;
+                }
+                state
+                    .read(|state| {
+                        async move { state.update_rollup_registration(body).await }.boxed()
+                    })
+                    .await
+            }
+            .boxed()
+        }
+    })?
     .get("rollup_registrations", |_req, state| {
         async move { state.get_all_rollup_registrations().await }.boxed()
+    })?
+    .stream("registration_changes", |req, state| {
+        async move {
+            let cursor = req.opt_integer_param("cursor")?;
+            let changes = state
+                .read(|state| {
+                    async move { state.subscribe_registration_changes(cursor).await }.boxed()
+                })
+                .await?;
+
+            Ok(changes.map(Ok))
+        }
+        .try_flatten_stream()
+        .boxed()
+    })?
+    .at("register_builder", move |req, state| {
+        let limits = limits.clone();
+        async move {
+            limits.check_request(&req)?;
+            let registration = req.body_json::<BuilderRegistration>()?;
+            if registration.verify() {
+                limits.check_key(registration.body.account)?;
+            }
+            state
+                .read(|state| async move { state.register_builder(registration).await }.boxed())
+                .await
+        }
+        .boxed()
+    })?
+    .get("builder_registration", |req, state| {
+        async move {
+            let account = req.string_param("account")?;
+            let account = account.parse().map_err(|err| SolverError::Custom {
+                status: StatusCode::BAD_REQUEST,
+                message: format!("invalid builder account {account}: {err}"),
+            })?;
+            state.get_builder_registration(account).await
+        }
+        .boxed()
+    })?
+    .get("builder_registrations", |_req, state| {
+        async move { state.get_all_builder_registrations().await }.boxed()
+    })?
+    .get("auction_builders", |req, state| {
+        async move {
+            let view_number = ViewNumber::new(req.integer_param::<_, u64>("view_number")?);
+            state.get_auction_builders(view_number).await
+        }
+        .boxed()
     })?;
     Ok(api)
 }
 
+/// Whether `signature` over `body` is by `key`.
+fn signed_by(
+    key: &PubKey,
+    signature: &<PubKey as SignatureKey>::PureAssembledSignatureType,
+    body: &impl Committable,
+) -> bool {
+    PubKey::validate(key, signature, body.commit().as_ref())
+}
+
 pub(crate) fn load_api<State: 'static, Error: 'static, Ver: StaticVersionType + 'static>(
     path: Option<impl AsRef<Path>>,
     default: &str,
@@ -168,3 +391,58 @@ fn load_toml(path: &Path) -> Result<Value, ApiError> {
         reason: err.to_string(),
     })
 }
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use vbs::version::StaticVersion;
+
+    use super::*;
+
+    #[test]
+    fn test_api_extensions_from_files() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let extension = dir.path().join("extension.toml");
+        fs::write(
+            &extension,
+            r#"
+[route.submit_bid]
+DOC = "Custom documentation"
+
+[route.builders]
+PATH = ["builders"]
+METHOD = "GET"
+DOC = "Route added by a deployment"
+"#,
+        )
+        .unwrap();
+
+        let options = ApiOptions::from_files(None, [&extension]).unwrap();
+        assert_eq!(options.extensions.len(), 1);
+
+        let mut toml: Value = toml::from_str(include_str!("../api/solver.toml")).unwrap();
+        merge_toml(&mut toml, options.extensions[0].clone());
+
+        // Extensions override existing fields and add new routes without dropping the rest
+        let routes = &toml["route"];
+        assert_eq!(
+            routes["submit_bid"]["DOC"].as_str(),
+            Some("Custom documentation")
+        );
+        assert_eq!(routes["submit_bid"]["METHOD"].as_str(), Some("POST"));
+        assert!(routes.get("builders").is_some());
+        assert!(routes.get("auction_results").is_some());
+
+        load_api::<(), SolverError, StaticVersion<0, 1>>(
+            options.api_path.as_ref(),
+            include_str!("../api/solver.toml"),
+            options.extensions,
+        )
+        .unwrap();
+
+        // A missing file is reported instead of falling back to the default specification
+        ApiOptions::from_files(None, [dir.path().join("missing.toml")]).unwrap_err();
+    }
+}
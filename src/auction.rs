@@ -0,0 +1,725 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    time::Instant,
+};
+
+use committable::{Commitment, Committable, RawCommitmentBuilder};
+use espresso_types::{
+    v0_3::{BidTx, RollupRegistration, SolverAuctionResults},
+    FeeAmount, NamespaceId,
+};
+use hotshot_types::{data::ViewNumber, traits::node_implementation::ConsensusTime};
+use serde::{Deserialize, Serialize};
+
+use crate::AuctionOptions;
+
+/// The exact set of bids and registrations an auction was computed from.
+///
+/// Bids are kept in tie-breaking order and registrations in namespace order, so the
+/// commitment only depends on the set of inputs and not on the order they arrived in.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AuctionInputs {
+    pub view_number: ViewNumber,
+    pub bids: Vec<BidTx>,
+    pub registrations: Vec<RollupRegistration>,
+}
+
+impl AuctionInputs {
+    /// Collects the inputs of the auction for `view_number` in canonical order.
+    ///
+    /// Bids for other views are dropped.
+    pub fn new(
+        view_number: ViewNumber,
+        bids: impl IntoIterator<Item = BidTx>,
+        registrations: impl IntoIterator<Item = RollupRegistration>,
+    ) -> Self {
+        let mut bids: Vec<(Vec<u8>, BidTx)> = bids
+            .into_iter()
+            .filter(|bid| bid.view() == view_number)
+            .map(|bid| (tie_break_key(view_number, &bid), bid))
+            .collect();
+        bids.sort_by(|(a, _), (b, _)| a.cmp(b));
+        bids.dedup_by(|(a, _), (b, _)| a == b);
+
+        let mut registrations: Vec<RollupRegistration> = registrations.into_iter().collect();
+        registrations.sort_by_key(|r| r.body.namespace_id);
+
+        Self {
+            view_number,
+            bids: bids.into_iter().map(|(_, bid)| bid).collect(),
+            registrations,
+        }
+    }
+}
+
+impl Committable for AuctionInputs {
+    fn commit(&self) -> Commitment<Self> {
+        let bids: Vec<_> = self.bids.iter().map(|bid| bid.commit()).collect();
+        let registrations: Vec<_> = self.registrations.iter().map(|r| r.body.commit()).collect();
+
+        RawCommitmentBuilder::new(&Self::tag())
+            .u64_field("view_number", self.view_number.u64())
+            .array_field("bids", &bids)
+            .array_field("registrations", &registrations)
+            .finalize()
+    }
+
+    fn tag() -> String {
+        "AUCTION_INPUTS".to_string()
+    }
+}
+
+/// How the winners of an auction were determined.
+///
+/// The greedy approximation stops on a time budget, so the number of improvement rounds it
+/// completed is recorded to make the computation reproducible.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WinnerDetermination {
+    Exact,
+    Greedy { rounds: u64 },
+}
+
+/// The results of an auction together with a commitment to the inputs they were computed from.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AuctionOutcome {
+    pub commitment: Commitment<AuctionInputs>,
+    pub inputs: AuctionInputs,
+    pub method: WinnerDetermination,
+    pub results: SolverAuctionResults,
+}
+
+impl AuctionOutcome {
+    /// Recomputes the results from the recorded inputs.
+    ///
+    /// Returns an error describing the first mismatch if the commitment or the results do not
+    /// match the inputs.
+    pub fn verify(&self) -> Result<(), String> {
+        let commitment = self.inputs.commit();
+        if commitment != self.commitment {
+            return Err(format!(
+                "input commitment mismatch: expected {}, computed {commitment}",
+                self.commitment
+            ));
+        }
+
+        let results = replay_auction(&self.inputs, self.method);
+        if results != self.results {
+            return Err(format!(
+                "results mismatch: expected {:?}, computed {results:?}",
+                self.results
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Deterministic tie-breaking rule: among bids that are otherwise equal, the one with the
+/// lower `hash(view_number, bid commitment)` ranks first.
+pub fn tie_break_key(view_number: ViewNumber, bid: &BidTx) -> Vec<u8> {
+    TieBreak {
+        view_number,
+        bid: bid.commit(),
+    }
+    .commit()
+    .as_ref()
+    .to_vec()
+}
+
+struct TieBreak {
+    view_number: ViewNumber,
+    bid: Commitment<BidTx>,
+}
+
+impl Committable for TieBreak {
+    fn commit(&self) -> Commitment<Self> {
+        RawCommitmentBuilder::new(&Self::tag())
+            .u64_field("view_number", self.view_number.u64())
+            .field("bid", self.bid)
+            .finalize()
+    }
+
+    fn tag() -> String {
+        "AUCTION_TIE_BREAK".to_string()
+    }
+}
+
+/// Computes the auction results from `inputs`.
+///
+/// Every bid covers a bundle of namespaces. Inactive registrations are ignored entirely, and a
+/// bid is only considered if all of its namespaces are active and its amount meets the combined
+/// reserve price of the bundle. The winners are the set of non-conflicting bids with the highest
+/// total amount: bid books with at most `exact_search_limit` bids are solved exactly, larger ones
+/// with a greedy approximation bounded by `greedy_time_budget`. Namespaces that no winning bid
+/// covers fall back to their `reserve_url`.
+pub fn compute_auction_results(inputs: AuctionInputs, options: &AuctionOptions) -> AuctionOutcome {
+    let candidates = candidates(&inputs);
+
+    let (winners, method) = if candidates.len() <= options.exact_search_limit {
+        (solve_exact(&candidates), WinnerDetermination::Exact)
+    } else {
+        let deadline = Instant::now() + options.greedy_time_budget;
+        let (winners, rounds) = solve_greedy(&candidates, GreedyLimit::Deadline(deadline));
+        (winners, WinnerDetermination::Greedy { rounds })
+    };
+
+    let results = auction_results(&inputs, &candidates, winners);
+
+    AuctionOutcome {
+        commitment: inputs.commit(),
+        inputs,
+        method,
+        results,
+    }
+}
+
+/// Recomputes the results of an auction from its inputs with the recorded `method`.
+pub fn replay_auction(inputs: &AuctionInputs, method: WinnerDetermination) -> SolverAuctionResults {
+    let candidates = candidates(inputs);
+
+    let winners = match method {
+        WinnerDetermination::Exact => solve_exact(&candidates),
+        WinnerDetermination::Greedy { rounds } => {
+            solve_greedy(&candidates, GreedyLimit::Rounds(rounds)).0
+        }
+    };
+
+    auction_results(inputs, &candidates, winners)
+}
+
+fn active_registrations(inputs: &AuctionInputs) -> BTreeMap<NamespaceId, &RollupRegistration> {
+    // BTreeMap keeps the reserve bids ordered by namespace id
+    inputs
+        .registrations
+        .iter()
+        .filter(|r| r.body.active)
+        .map(|r| (r.body.namespace_id, r))
+        .collect()
+}
+
+fn candidates(inputs: &AuctionInputs) -> Vec<Candidate> {
+    let active = active_registrations(inputs);
+
+    // Bids that would overflow the total of all candidate amounts are dropped, so any sum over
+    // a subset of the candidates below is guaranteed to fit.
+    let mut total = FeeAmount::from(0_u64);
+    inputs
+        .bids
+        .iter()
+        .enumerate()
+        .filter_map(|(rank, bid)| Candidate::new(bid, rank, &active))
+        .filter(|candidate| match total.checked_add(candidate.amount) {
+            Some(sum) => {
+                total = sum;
+                true
+            }
+            None => {
+                tracing::warn!("dropping bid with amount {:?}: overflow", candidate.amount);
+                false
+            }
+        })
+        .collect()
+}
+
+fn auction_results(
+    inputs: &AuctionInputs,
+    candidates: &[Candidate],
+    winners: Vec<usize>,
+) -> SolverAuctionResults {
+    let covered: HashSet<NamespaceId> = winners
+        .iter()
+        .flat_map(|&i| candidates[i].namespaces.iter().copied())
+        .collect();
+
+    let reserve_bids = active_registrations(inputs)
+        .into_iter()
+        .filter(|(namespace_id, _)| !covered.contains(namespace_id))
+        .map(|(namespace_id, r)| (namespace_id, r.body.reserve_url.clone()))
+        .collect();
+
+    let mut winners = winners;
+    winners.sort_unstable();
+
+    let winning_bids = winners
+        .into_iter()
+        .map(|i| candidates[i].bid.clone())
+        .collect();
+
+    SolverAuctionResults::new(inputs.view_number, winning_bids, reserve_bids)
+}
+
+/// A bid that is eligible to win the auction.
+struct Candidate<'a> {
+    bid: &'a BidTx,
+    /// Position of the bid in the tie-breaking order of the inputs
+    rank: usize,
+    namespaces: BTreeSet<NamespaceId>,
+    amount: FeeAmount,
+}
+
+impl<'a> Candidate<'a> {
+    fn new(
+        bid: &'a BidTx,
+        rank: usize,
+        active: &BTreeMap<NamespaceId, &RollupRegistration>,
+    ) -> Option<Self> {
+        let namespaces: BTreeSet<NamespaceId> = bid.namespaces().iter().copied().collect();
+
+        if namespaces.is_empty() {
+            return None;
+        }
+
+        let mut reserve_price = FeeAmount::from(0_u64);
+        for namespace_id in &namespaces {
+            let registration = active.get(namespace_id)?;
+            reserve_price = reserve_price.checked_add(registration.body.reserve_price)?;
+        }
+
+        let amount = bid.amount();
+        (amount >= reserve_price).then_some(Self {
+            bid,
+            rank,
+            namespaces,
+            amount,
+        })
+    }
+
+    fn conflicts(&self, other: &Candidate) -> bool {
+        !self.namespaces.is_disjoint(&other.namespaces)
+    }
+}
+
+fn add(a: FeeAmount, b: FeeAmount) -> FeeAmount {
+    a.checked_add(b)
+        .expect("sum of candidate amounts is bounded by their total")
+}
+
+/// Candidate indices ordered by decreasing amount, preferring smaller bundles and then the
+/// tie-breaking order on ties.
+fn by_amount(candidates: &[Candidate]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..candidates.len()).collect();
+    order.sort_by(|&a, &b| {
+        candidates[b]
+            .amount
+            .cmp(&candidates[a].amount)
+            .then(
+                candidates[a]
+                    .namespaces
+                    .len()
+                    .cmp(&candidates[b].namespaces.len()),
+            )
+            .then(candidates[a].rank.cmp(&candidates[b].rank))
+    });
+    order
+}
+
+/// Finds the optimal set of non-conflicting candidates with a depth-first branch and bound.
+fn solve_exact(candidates: &[Candidate]) -> Vec<usize> {
+    let order = by_amount(candidates);
+
+    // `remaining[i]` is the sum of the amounts from position `i` onwards, an upper bound on what
+    // the rest of the search can still add.
+    let mut remaining = vec![FeeAmount::from(0_u64); order.len() + 1];
+    for i in (0..order.len()).rev() {
+        remaining[i] = add(remaining[i + 1], candidates[order[i]].amount);
+    }
+
+    let mut search = ExactSearch {
+        candidates,
+        order,
+        remaining,
+        current: Vec::new(),
+        best: Vec::new(),
+        best_value: FeeAmount::from(0_u64),
+    };
+    search.run(0, FeeAmount::from(0_u64));
+
+    search.best
+}
+
+struct ExactSearch<'a, 'b> {
+    candidates: &'b [Candidate<'a>],
+    order: Vec<usize>,
+    remaining: Vec<FeeAmount>,
+    current: Vec<usize>,
+    best: Vec<usize>,
+    best_value: FeeAmount,
+}
+
+impl ExactSearch<'_, '_> {
+    fn run(&mut self, depth: usize, value: FeeAmount) {
+        if value > self.best_value {
+            self.best_value = value;
+            self.best = self.current.clone();
+        }
+
+        if depth == self.order.len() || add(value, self.remaining[depth]) <= self.best_value {
+            return;
+        }
+
+        let candidates = self.candidates;
+        let index = self.order[depth];
+        let candidate = &candidates[index];
+
+        if self
+            .current
+            .iter()
+            .all(|&i| !candidates[i].conflicts(candidate))
+        {
+            self.current.push(index);
+            self.run(depth + 1, add(value, candidate.amount));
+            self.current.pop();
+        }
+
+        self.run(depth + 1, value);
+    }
+}
+
+enum GreedyLimit {
+    Deadline(Instant),
+    Rounds(u64),
+}
+
+/// Approximates the optimal set of non-conflicting candidates.
+///
+/// Candidates are first taken greedily in decreasing order of amount. Afterwards, each round
+/// lets unselected candidates replace the selected ones they conflict with whenever they are
+/// worth more. Rounds continue until one makes no replacement or `limit` is reached; a deadline
+/// is also checked within rounds, and a round it interrupts is undone. The number of completed
+/// rounds is returned alongside the winners.
+fn solve_greedy(candidates: &[Candidate], limit: GreedyLimit) -> (Vec<usize>, u64) {
+    let order = by_amount(candidates);
+
+    let mut selected: Vec<usize> = Vec::new();
+    for &i in &order {
+        if selected
+            .iter()
+            .all(|&j| !candidates[j].conflicts(&candidates[i]))
+        {
+            selected.push(i);
+        }
+    }
+
+    let past_deadline = || match limit {
+        GreedyLimit::Deadline(deadline) => Instant::now() >= deadline,
+        GreedyLimit::Rounds(_) => false,
+    };
+
+    let mut rounds = 0;
+    'rounds: loop {
+        let exhausted = match limit {
+            GreedyLimit::Deadline(_) => past_deadline(),
+            GreedyLimit::Rounds(max) => rounds >= max,
+        };
+        if exhausted {
+            break;
+        }
+
+        let start = selected.clone();
+        let mut improved = false;
+
+        for &i in &order {
+            // A round over a large book can take longer than the whole budget. Only completed
+            // rounds are recorded and replayed, so a round cut short is undone.
+            if past_deadline() {
+                selected = start;
+                break 'rounds;
+            }
+            if selected.contains(&i) {
+                continue;
+            }
+
+            let conflicting: Vec<usize> = selected
+                .iter()
+                .copied()
+                .filter(|&j| candidates[j].conflicts(&candidates[i]))
+                .collect();
+
+            let displaced = conflicting.iter().fold(FeeAmount::from(0_u64), |sum, &j| {
+                add(sum, candidates[j].amount)
+            });
+
+            if candidates[i].amount > displaced {
+                selected.retain(|j| !conflicting.contains(j));
+                selected.push(i);
+                improved = true;
+            }
+        }
+
+        rounds += 1;
+        if !improved {
+            break;
+        }
+    }
+
+    (selected, rounds)
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use committable::Committable;
+    use espresso_types::v0_3::{BidTx, RollupRegistration, SolverAuctionResults};
+    use hotshot_types::{data::ViewNumber, traits::node_implementation::ConsensusTime};
+
+    use super::{compute_auction_results, tie_break_key, AuctionInputs, WinnerDetermination};
+    use crate::{
+        testing::{bid, registration},
+        AuctionOptions,
+    };
+
+    fn solve(
+        view: ViewNumber,
+        bids: &[BidTx],
+        registrations: &[RollupRegistration],
+        options: &AuctionOptions,
+    ) -> SolverAuctionResults {
+        let inputs = AuctionInputs::new(view, bids.to_vec(), registrations.to_vec());
+        compute_auction_results(inputs, options).results
+    }
+
+    #[test]
+    fn test_bids_below_reserve_price_are_discarded() {
+        let registrations = vec![registration(1, 100, true), registration(2, 100, true)];
+
+        let low = bid(1, &[1], 50);
+        let high = bid(1, &[2], 150);
+
+        let results = solve(
+            ViewNumber::new(1),
+            &[low, high.clone()],
+            &registrations,
+            &AuctionOptions::default(),
+        );
+
+        assert_eq!(results.winning_bids(), &[high]);
+        assert_eq!(
+            results.reserve_bids(),
+            &[(1_u64.into(), registrations[0].body.reserve_url.clone())]
+        );
+    }
+
+    #[test]
+    fn test_highest_bid_wins() {
+        let registrations = vec![registration(1, 100, true)];
+
+        let first = bid(1, &[1], 150);
+        let second = bid(1, &[1], 300);
+
+        let results = solve(
+            ViewNumber::new(1),
+            &[first, second.clone()],
+            &registrations,
+            &AuctionOptions::default(),
+        );
+
+        assert_eq!(results.winning_bids(), &[second]);
+        assert!(results.reserve_bids().is_empty());
+    }
+
+    #[test]
+    fn test_inactive_registrations_are_excluded() {
+        let registrations = vec![registration(1, 100, true), registration(2, 100, false)];
+
+        let results = solve(
+            ViewNumber::new(1),
+            &[bid(1, &[2], 500), bid(1, &[1, 2], 1000)],
+            &registrations,
+            &AuctionOptions::default(),
+        );
+
+        assert!(results.winning_bids().is_empty());
+        assert_eq!(
+            results.reserve_bids(),
+            &[(1_u64.into(), registrations[0].body.reserve_url.clone())]
+        );
+    }
+
+    #[test]
+    fn test_bundle_reserve_price_is_combined() {
+        let registrations = vec![registration(1, 100, true), registration(2, 100, true)];
+
+        // 150 covers either reserve price but not both
+        let results = solve(
+            ViewNumber::new(1),
+            &[bid(1, &[1, 2], 150)],
+            &registrations,
+            &AuctionOptions::default(),
+        );
+
+        assert!(results.winning_bids().is_empty());
+        assert_eq!(results.reserve_bids().len(), 2);
+    }
+
+    #[test]
+    fn test_bundle_beats_single_slots() {
+        let registrations = vec![
+            registration(1, 100, true),
+            registration(2, 100, true),
+            registration(3, 100, true),
+        ];
+
+        let bundle = bid(1, &[1, 2], 1000);
+        let single_1 = bid(1, &[1], 600);
+        let single_2 = bid(1, &[2], 300);
+        let single_3 = bid(1, &[3], 200);
+
+        let bids = [bundle.clone(), single_1, single_2, single_3.clone()];
+
+        for options in [
+            AuctionOptions::default(),
+            AuctionOptions {
+                exact_search_limit: 0,
+                greedy_time_budget: Duration::from_millis(100),
+                ..Default::default()
+            },
+        ] {
+            let results = solve(ViewNumber::new(1), &bids, &registrations, &options);
+
+            let mut winners = results.winning_bids().to_vec();
+            winners.sort_by_key(|bid| bid.amount());
+            assert_eq!(winners, vec![single_3.clone(), bundle.clone()]);
+            assert!(results.reserve_bids().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_exact_search_finds_optimum() {
+        let registrations = vec![
+            registration(1, 0, true),
+            registration(2, 0, true),
+            registration(3, 0, true),
+        ];
+
+        // Taking the largest bid first yields 500, the optimum is 300 + 300 = 600
+        let large = bid(1, &[1, 2, 3], 500);
+        let left = bid(1, &[1, 2], 300);
+        let right = bid(1, &[3], 300);
+
+        let results = solve(
+            ViewNumber::new(1),
+            &[large, left.clone(), right.clone()],
+            &registrations,
+            &AuctionOptions::default(),
+        );
+
+        let winners = results.winning_bids();
+        assert_eq!(winners.len(), 2);
+        assert!(winners.contains(&left));
+        assert!(winners.contains(&right));
+    }
+
+    #[test]
+    fn test_greedy_stops_at_deadline() {
+        let registrations = vec![
+            registration(1, 0, true),
+            registration(2, 0, true),
+            registration(3, 0, true),
+        ];
+        let large = bid(1, &[1, 2, 3], 500);
+        let bids = vec![large.clone(), bid(1, &[1, 2], 300), bid(1, &[3], 300)];
+
+        // Without any budget no improvement round runs, so the largest bid keeps its place
+        let options = AuctionOptions {
+            exact_search_limit: 0,
+            greedy_time_budget: Duration::ZERO,
+            ..Default::default()
+        };
+        let inputs = AuctionInputs::new(ViewNumber::new(1), bids, registrations);
+        let outcome = compute_auction_results(inputs, &options);
+
+        assert_eq!(outcome.method, WinnerDetermination::Greedy { rounds: 0 });
+        assert_eq!(outcome.results.winning_bids(), &[large]);
+        outcome.verify().unwrap();
+    }
+
+    #[test]
+    fn test_equal_bids_use_tie_break_rule() {
+        let registrations = vec![registration(1, 100, true)];
+
+        let a = bid(1, &[1], 300);
+        let b = bid(1, &[1], 300);
+
+        let view_number = ViewNumber::new(1);
+        let expected = if tie_break_key(view_number, &a) < tie_break_key(view_number, &b) {
+            a.clone()
+        } else {
+            b.clone()
+        };
+
+        // The winner does not depend on the order the bids were submitted in
+        for bids in [[a.clone(), b.clone()], [b.clone(), a.clone()]] {
+            let results = solve(
+                view_number,
+                &bids,
+                &registrations,
+                &AuctionOptions::default(),
+            );
+            assert_eq!(results.winning_bids(), &[expected.clone()]);
+        }
+    }
+
+    #[test]
+    fn test_inputs_commitment_is_order_independent() {
+        let registrations = vec![registration(1, 100, true), registration(2, 100, true)];
+        let bids = vec![bid(1, &[1], 300), bid(1, &[2], 300), bid(1, &[1, 2], 500)];
+
+        let view_number = ViewNumber::new(1);
+        let inputs = AuctionInputs::new(view_number, bids.clone(), registrations.clone());
+
+        let mut reversed_bids = bids.clone();
+        reversed_bids.reverse();
+        let mut reversed_registrations = registrations.clone();
+        reversed_registrations.reverse();
+        let reversed = AuctionInputs::new(view_number, reversed_bids, reversed_registrations);
+
+        assert_eq!(inputs, reversed);
+        assert_eq!(inputs.commit(), reversed.commit());
+
+        // Any change to the bid set changes the commitment
+        let mut more_bids = bids;
+        more_bids.push(bid(1, &[2], 10));
+        let changed = AuctionInputs::new(view_number, more_bids, registrations);
+        assert_ne!(inputs.commit(), changed.commit());
+    }
+
+    #[test]
+    fn test_verify_outcome() {
+        let registrations = vec![
+            registration(1, 100, true),
+            registration(2, 100, true),
+            registration(3, 100, false),
+        ];
+        let bids: Vec<BidTx> = (0..30)
+            .map(|i| bid(1, &[i % 3 + 1, (i + 1) % 3 + 1], 200 + i * 10))
+            .collect();
+
+        for options in [
+            AuctionOptions::default(),
+            AuctionOptions {
+                exact_search_limit: 0,
+                greedy_time_budget: Duration::from_millis(100),
+                ..Default::default()
+            },
+        ] {
+            let inputs =
+                AuctionInputs::new(ViewNumber::new(1), bids.clone(), registrations.clone());
+            let mut outcome = compute_auction_results(inputs, &options);
+            outcome.verify().unwrap();
+
+            // Tampering with the inputs breaks the commitment
+            let mut tampered = outcome.clone();
+            tampered.inputs.bids.pop();
+            tampered.verify().unwrap_err();
+
+            // Tampering with the results is detected by recomputing them
+            outcome.results = SolverAuctionResults::new(
+                ViewNumber::new(1),
+                Vec::new(),
+                outcome.results.reserve_bids().to_vec(),
+            );
+            outcome.verify().unwrap_err();
+        }
+    }
+}
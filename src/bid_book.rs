@@ -0,0 +1,271 @@
+//! Bids of the auctions that are still open.
+//!
+//! The book is sharded by builder account, so bids of different builders are added without
+//! contending for the same lock, and it tracks which views are closed for bidding itself. A bid
+//! is only added while its view is open, checked under the lock of its shard, so a bid accepted
+//! concurrently with the auction for its view being closed is either in the auction's inputs or
+//! rejected, never silently dropped.
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap},
+    hash::{Hash, Hasher},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, MutexGuard,
+    },
+};
+
+use espresso_types::{v0_3::BidTx, FeeAccount};
+use hotshot_types::{data::ViewNumber, traits::node_implementation::ConsensusTime};
+
+use crate::{AuctionOptions, SolverError, SolverResult};
+
+const SHARDS: usize = 16;
+
+// A builder may submit several bids per view, each for a different bundle of namespaces
+type Shard = HashMap<ViewNumber, HashMap<FeeAccount, Vec<BidTx>>>;
+
+pub struct BidBook {
+    shards: Vec<Mutex<Shard>>,
+    // One past the latest view closed for bidding, zero if none was
+    closed: AtomicU64,
+}
+
+impl Default for BidBook {
+    fn default() -> Self {
+        Self {
+            shards: (0..SHARDS).map(|_| Mutex::default()).collect(),
+            closed: AtomicU64::new(0),
+        }
+    }
+}
+
+impl BidBook {
+    fn shard(&self, account: &FeeAccount) -> MutexGuard<'_, Shard> {
+        let mut hasher = DefaultHasher::new();
+        account.hash(&mut hasher);
+        lock(&self.shards[hasher.finish() as usize % SHARDS])
+    }
+
+    fn shards(&self) -> impl Iterator<Item = MutexGuard<'_, Shard>> {
+        self.shards.iter().map(lock)
+    }
+
+    /// Latest view whose auction no longer accepts bids.
+    pub fn closed_view(&self) -> Option<ViewNumber> {
+        match self.closed.load(Ordering::SeqCst) {
+            0 => None,
+            view => Some(ViewNumber::new(view - 1)),
+        }
+    }
+
+    /// Views bids are currently accepted for, as configured by `options`.
+    pub fn open_views(&self, options: &AuctionOptions) -> (u64, u64) {
+        let first = self.closed.load(Ordering::SeqCst);
+        (first, first.saturating_add(options.max_view_lookahead))
+    }
+
+    /// Adds a bid for an open view, unless the builder already submitted as many bids for that
+    /// view as `options` allow. Submitting the same bid again has no effect.
+    pub fn insert(&self, bid: BidTx, options: &AuctionOptions) -> SolverResult<()> {
+        let mut shard = self.shard(&bid.account());
+
+        // Checked under the shard lock, see `close`
+        let view = bid.view().u64();
+        let (first, last) = self.open_views(options);
+        if !(first..=last).contains(&view) {
+            return Err(SolverError::BidViewOutOfRange { view, first, last });
+        }
+
+        let max = options.max_bids_per_builder;
+        let bids = shard
+            .entry(bid.view())
+            .or_default()
+            .entry(bid.account())
+            .or_default();
+
+        if !bids.contains(&bid) {
+            if bids.len() >= max {
+                return Err(SolverError::TooManyBids { view, max });
+            }
+
+            bids.push(bid);
+        }
+
+        Ok(())
+    }
+
+    /// Opens bidding from `view_number` on, closing every view before it without an auction.
+    /// Views that are already closed stay closed.
+    pub fn open_from(&self, view_number: ViewNumber) {
+        self.closed.fetch_max(view_number.u64(), Ordering::SeqCst);
+    }
+
+    /// Closes bidding for `view_number` and every view before it, and returns the bids the
+    /// auction for `view_number` is computed from.
+    pub fn close(&self, view_number: ViewNumber) -> Vec<BidTx> {
+        self.closed
+            .fetch_max(view_number.u64() + 1, Ordering::SeqCst);
+
+        // Any insert that saw the view open holds its shard lock until the bid is added, so
+        // taking every shard lock after closing the view observes all of them
+        self.bids(view_number)
+    }
+
+    /// The bids for `view_number` received so far.
+    pub fn bids(&self, view_number: ViewNumber) -> Vec<BidTx> {
+        self.shards()
+            .flat_map(|shard| {
+                shard
+                    .get(&view_number)
+                    .map(|bids| bids.values().flatten().cloned().collect::<Vec<_>>())
+                    .unwrap_or_default()
+            })
+            .collect()
+    }
+
+    /// Drops the bids for `view_number` and every view before it, and returns how many bids
+    /// were dropped for each view before it, whose auctions were never closed.
+    pub fn prune(&self, view_number: ViewNumber) -> BTreeMap<ViewNumber, usize> {
+        let mut skipped = BTreeMap::new();
+        for mut shard in self.shards() {
+            shard.retain(|view, bids| {
+                if *view < view_number {
+                    *skipped.entry(*view).or_default() +=
+                        bids.values().map(Vec::len).sum::<usize>();
+                }
+                *view > view_number
+            });
+        }
+
+        skipped.retain(|_, count| *count > 0);
+        skipped
+    }
+
+    /// Drops every bid of the builder `account`.
+    pub fn remove_builder(&self, account: &FeeAccount) {
+        for bids in self.shard(account).values_mut() {
+            bids.remove(account);
+        }
+    }
+}
+
+fn lock(shard: &Mutex<Shard>) -> MutexGuard<'_, Shard> {
+    // A panic while holding the lock cannot leave a shard half-updated
+    shard.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::BTreeMap, sync::Arc, thread};
+
+    use espresso_types::EthKeyPair;
+    use hotshot_types::{data::ViewNumber, traits::node_implementation::ConsensusTime};
+
+    use super::BidBook;
+    use crate::{
+        testing::{bid, signed_bid},
+        AuctionOptions, SolverError,
+    };
+
+    #[test]
+    fn test_bid_book() {
+        let book = BidBook::default();
+        let options = AuctionOptions {
+            max_bids_per_builder: 2,
+            ..Default::default()
+        };
+        let key = EthKeyPair::random();
+
+        book.insert(signed_bid(&key, 1, &[1], 100), &options)
+            .unwrap();
+        // Duplicates are ignored and do not count against the limit
+        book.insert(signed_bid(&key, 1, &[1], 100), &options)
+            .unwrap();
+        book.insert(signed_bid(&key, 1, &[1], 200), &options)
+            .unwrap();
+        match book
+            .insert(signed_bid(&key, 1, &[1], 300), &options)
+            .unwrap_err()
+        {
+            SolverError::TooManyBids { view: 1, max: 2 } => {}
+            err => panic!("err {err:?}"),
+        }
+        book.insert(bid(2, &[1], 100), &options).unwrap();
+
+        assert_eq!(book.bids(ViewNumber::new(1)).len(), 2);
+
+        assert_eq!(book.closed_view(), None);
+        assert_eq!(book.close(ViewNumber::new(1)).len(), 2);
+        assert_eq!(book.closed_view(), Some(ViewNumber::new(1)));
+        match book
+            .insert(signed_bid(&key, 1, &[1], 400), &options)
+            .unwrap_err()
+        {
+            SolverError::BidViewOutOfRange {
+                view: 1, first: 2, ..
+            } => {}
+            err => panic!("err {err:?}"),
+        }
+
+        assert!(book.prune(ViewNumber::new(1)).is_empty());
+        assert!(book.bids(ViewNumber::new(1)).is_empty());
+        assert_eq!(book.bids(ViewNumber::new(2)).len(), 1);
+
+        book.remove_builder(&key.fee_account());
+        book.insert(signed_bid(&key, 2, &[1], 100), &options)
+            .unwrap();
+        book.remove_builder(&key.fee_account());
+        assert_eq!(book.bids(ViewNumber::new(2)).len(), 1);
+
+        // Pruning past a view that was never closed reports its bids
+        book.insert(signed_bid(&key, 3, &[1], 100), &options)
+            .unwrap();
+        assert_eq!(
+            book.prune(ViewNumber::new(3)),
+            BTreeMap::from([(ViewNumber::new(2), 1)])
+        );
+
+        // Opening bidding from a later view closes the ones before it
+        book.open_from(ViewNumber::new(10));
+        assert_eq!(book.closed_view(), Some(ViewNumber::new(9)));
+        match book
+            .insert(signed_bid(&key, 9, &[1], 100), &options)
+            .unwrap_err()
+        {
+            SolverError::BidViewOutOfRange {
+                view: 9, first: 10, ..
+            } => {}
+            err => panic!("err {err:?}"),
+        }
+        book.insert(signed_bid(&key, 10, &[1], 100), &options)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_close_during_inserts() {
+        let book = Arc::new(BidBook::default());
+        let options = Arc::new(AuctionOptions::default());
+
+        let builders: Vec<_> = (0..8)
+            .map(|_| {
+                let book = book.clone();
+                let options = options.clone();
+                thread::spawn(move || {
+                    let key = EthKeyPair::random();
+                    (1..=5)
+                        .filter(|amount| {
+                            book.insert(signed_bid(&key, 1, &[1], *amount), &options)
+                                .is_ok()
+                        })
+                        .count()
+                })
+            })
+            .collect();
+
+        let closed = book.close(ViewNumber::new(1));
+        let accepted: usize = builders.into_iter().map(|t| t.join().unwrap()).sum();
+
+        // Every accepted bid is part of the auction
+        assert_eq!(closed.len(), accepted);
+    }
+}
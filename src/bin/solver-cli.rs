@@ -0,0 +1,303 @@
+//! Command line tool for rollup operators and builders.
+//!
+//! Builds and signs the request bodies the solver API expects, sends them and prints the JSON
+//! responses.
+use std::{
+    fs::{self, File},
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use clap::{Parser, Subcommand};
+use espresso_types::{
+    v0_3::{BidTxBody, RollupRegistrationBody, RollupUpdatebody},
+    EthKeyPair, FeeAmount, PubKey,
+};
+use hotshot::types::{BLSPubKey, SignatureKey};
+use hotshot_types::{
+    data::ViewNumber, signature_key::BLSPrivKey, traits::node_implementation::ConsensusTime,
+};
+use marketplace_solver::{
+    client::{sign_registration, sign_update, SolverClient},
+    recording::record_events,
+    signing::{read_private_key, write_private_key},
+    EventsServiceClient,
+};
+use serde::Serialize;
+use tide_disco::Url;
+
+#[derive(Parser, Clone, Debug)]
+struct Args {
+    /// URL of the solver API, including the module prefix
+    #[clap(
+        long,
+        env = "MARKETPLACE_SOLVER_URL",
+        default_value = "http://localhost:7777/solver_api"
+    )]
+    url: Url,
+
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Clone, Debug)]
+enum Command {
+    /// Generate a BLS key pair for signing rollup registrations and print the public key
+    GenerateKey {
+        /// New file to write the private key to, readable only by the current user
+        #[clap(long)]
+        out: PathBuf,
+    },
+    /// Register a rollup, signing the registration with the key in `key_file`
+    RegisterRollup {
+        #[clap(long)]
+        key_file: PathBuf,
+        #[clap(long)]
+        namespace_id: u64,
+        #[clap(long)]
+        reserve_url: Url,
+        /// Reserve price, in Wei
+        #[clap(long)]
+        reserve_price: FeeAmount,
+        #[clap(long, default_value_t = false)]
+        inactive: bool,
+        /// Additional keys allowed to update the registration
+        #[clap(long, value_delimiter = ',')]
+        signature_keys: Vec<PubKey>,
+        #[clap(long, default_value = "")]
+        text: String,
+    },
+    /// Update selected fields of a rollup registration
+    UpdateRollup {
+        #[clap(long)]
+        key_file: PathBuf,
+        #[clap(long)]
+        namespace_id: u64,
+        #[clap(long)]
+        reserve_url: Option<Url>,
+        /// Reserve price, in Wei
+        #[clap(long)]
+        reserve_price: Option<FeeAmount>,
+        #[clap(long)]
+        active: Option<bool>,
+        /// Replaces the keys allowed to update the registration; must include the signing key
+        #[clap(long, value_delimiter = ',')]
+        signature_keys: Option<Vec<PubKey>>,
+        #[clap(long)]
+        text: Option<String>,
+    },
+    /// List all rollup registrations
+    ListRollups,
+    /// Submit a bid, signed with the builder account derived from a mnemonic
+    SubmitBid {
+        /// File containing the mnemonic of the builder's account
+        #[clap(long)]
+        mnemonic_file: PathBuf,
+        #[clap(long, default_value_t = 0)]
+        account_index: u32,
+        #[clap(long)]
+        view: u64,
+        #[clap(long, value_delimiter = ',', required = true)]
+        namespaces: Vec<u64>,
+        /// Bid amount, in Wei
+        #[clap(long)]
+        amount: FeeAmount,
+        #[clap(long)]
+        builder_url: Url,
+    },
+    /// Fetch the signed results of an auction
+    Results {
+        #[clap(long)]
+        view: u64,
+        /// Fetch the current results of an open auction as the leader of the view, signing the
+        /// request with the key in this file
+        #[clap(long)]
+        leader_key_file: Option<PathBuf>,
+    },
+    /// Fetch the inputs and results of a finalized auction, for offline verification
+    Outcome {
+        #[clap(long)]
+        view: u64,
+    },
+    /// Record the event stream of a HotShot events service, for replay in tests
+    RecordEvents {
+        #[clap(long, env = "HOTSHOT_EVENTS_API_URL")]
+        events_url: Url,
+        #[clap(long)]
+        out: PathBuf,
+        /// Stop after this many events
+        #[clap(long)]
+        count: Option<usize>,
+    },
+}
+
+/// Generates a key pair, writes the private key to a new file at `out` and returns the public key.
+fn generate_key(out: &Path) -> anyhow::Result<BLSPubKey> {
+    let private_key = BLSPrivKey::generate(&mut rand::thread_rng());
+    write_private_key(out, &private_key)?;
+
+    Ok(BLSPubKey::from_private(&private_key))
+}
+
+fn print_json(value: &impl Serialize) -> anyhow::Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+#[async_std::main]
+async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    let client = SolverClient::new(args.url);
+
+    match args.command {
+        Command::GenerateKey { out } => println!("{}", generate_key(&out)?),
+        Command::RegisterRollup {
+            key_file,
+            namespace_id,
+            reserve_url,
+            reserve_price,
+            inactive,
+            mut signature_keys,
+            text,
+        } => {
+            let private_key = read_private_key(&key_file)?;
+            let signature_key = BLSPubKey::from_private(&private_key);
+            if !signature_keys.contains(&signature_key) {
+                signature_keys.push(signature_key);
+            }
+
+            let body = RollupRegistrationBody {
+                namespace_id: namespace_id.into(),
+                reserve_url,
+                reserve_price,
+                active: !inactive,
+                signature_keys,
+                text,
+                signature_key,
+            };
+            let registration = sign_registration(body, &private_key)?;
+
+            print_json(&client.register_rollup(&registration).await?)?;
+        }
+        Command::UpdateRollup {
+            key_file,
+            namespace_id,
+            reserve_url,
+            reserve_price,
+            active,
+            signature_keys,
+            text,
+        } => {
+            let private_key = read_private_key(&key_file)?;
+
+            let body = RollupUpdatebody {
+                namespace_id: namespace_id.into(),
+                reserve_url,
+                reserve_price,
+                active,
+                signature_keys,
+                signature_key: BLSPubKey::from_private(&private_key),
+                text,
+            };
+            let update = sign_update(body, &private_key)?;
+
+            print_json(&client.update_rollup(&update).await?)?;
+        }
+        Command::ListRollups => print_json(&client.rollup_registrations().await?)?,
+        Command::SubmitBid {
+            mnemonic_file,
+            account_index,
+            view,
+            namespaces,
+            amount,
+            builder_url,
+        } => {
+            let mnemonic = fs::read_to_string(&mnemonic_file)
+                .with_context(|| format!("failed to read {}", mnemonic_file.display()))?;
+            let key = EthKeyPair::from_mnemonic(mnemonic.trim(), account_index)
+                .context("invalid builder mnemonic")?;
+
+            let bid = BidTxBody::new(
+                key.fee_account(),
+                amount,
+                ViewNumber::new(view),
+                namespaces.into_iter().map(Into::into).collect(),
+                builder_url,
+            )
+            .signed(&key)
+            .context("failed to sign bid")?;
+
+            client.submit_bid(&bid).await?;
+            print_json(&bid)?;
+        }
+        Command::Results {
+            view,
+            leader_key_file,
+        } => {
+            let view = ViewNumber::new(view);
+            let results = match leader_key_file {
+                Some(path) => {
+                    let leader_key = read_private_key(&path)?;
+                    client
+                        .auction_results_permissioned(view, &leader_key)
+                        .await?
+                }
+                None => client.auction_results(view).await?,
+            };
+
+            print_json(&results)?;
+        }
+        Command::Outcome { view } => {
+            print_json(&client.auction_outcome(ViewNumber::new(view)).await?)?
+        }
+        Command::RecordEvents {
+            events_url,
+            out,
+            count,
+        } => {
+            let file = File::create(&out)
+                .with_context(|| format!("failed to create {}", out.display()))?;
+            let events = EventsServiceClient::new(events_url).await;
+
+            let recorded = record_events(events, BufWriter::new(file), count).await?;
+            println!("recorded {recorded} events to {}", out.display());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+    use hotshot::types::{BLSPubKey, SignatureKey};
+    use marketplace_solver::signing::read_private_key;
+
+    use super::{generate_key, Args, Command};
+
+    #[test]
+    fn test_generate_key_requires_out() {
+        Args::try_parse_from(["solver-cli", "generate-key"]).unwrap_err();
+
+        let args = Args::try_parse_from(["solver-cli", "generate-key", "--out", "key"]).unwrap();
+        match args.command {
+            Command::GenerateKey { out } => assert_eq!(out.to_str(), Some("key")),
+            command => panic!("command {command:?}"),
+        }
+    }
+
+    #[test]
+    fn test_generate_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("key");
+
+        let public_key = generate_key(&path).unwrap();
+        let stored = || BLSPubKey::from_private(&read_private_key(&path).unwrap());
+        assert_eq!(stored(), public_key);
+
+        // Generating again must not replace the key
+        generate_key(&path).unwrap_err();
+        assert_eq!(stored(), public_key);
+    }
+}
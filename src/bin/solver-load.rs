@@ -0,0 +1,199 @@
+//! Load generator for bid ingestion.
+//!
+//! Starts a solver with a mock events service, registers the rollups of a synthetic workload and
+//! submits every builder's bids for a number of views through the solver API, with a bounded
+//! number of requests in flight. After each view the auction is finalized directly on the solver
+//! state. Reports submission latency percentiles, throughput, rejections by reason and how long
+//! finalization took.
+//!
+//! With `--register-during-bids`, a rollup is registered while the bids of each view are being
+//! submitted, so comparing the throughput with and without it shows how much registration
+//! writes slow down bid ingestion.
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use clap::Parser;
+use futures::stream::{self, StreamExt};
+use hotshot_types::{data::ViewNumber, traits::node_implementation::ConsensusTime};
+use marketplace_solver::{
+    client::SolverClient,
+    database::mock::setup_mock_database,
+    metrics::rejection_reason,
+    mock::MockEvents,
+    parse_duration,
+    state::UpdateSolverState,
+    storage::{MemoryStorage, SolverStorage},
+    testing::MockSolver,
+    workload::{Workload, WorkloadOptions},
+    ApiOptions, AuctionOptions, RateLimitOptions,
+};
+
+#[derive(Parser, Clone, Debug)]
+struct Args {
+    /// Number of views to submit bids for, starting at view 1
+    #[clap(long, default_value_t = 10)]
+    views: u64,
+
+    /// Maximum number of requests in flight
+    #[clap(long, default_value_t = 64)]
+    concurrency: usize,
+
+    /// Run against a test Postgres database instead of the in-memory storage
+    #[clap(long, default_value_t = false)]
+    postgres: bool,
+
+    /// Latency added to every access of the in-memory storage
+    #[clap(long, value_parser = parse_duration)]
+    storage_latency: Option<Duration>,
+
+    /// Register a new rollup concurrently with the bids of every view
+    #[clap(long, default_value_t = false)]
+    register_during_bids: bool,
+
+    #[clap(flatten)]
+    workload: WorkloadOptions,
+
+    #[clap(flatten)]
+    limits: RateLimitOptions,
+
+    #[clap(flatten)]
+    auction_options: AuctionOptions,
+}
+
+#[async_std::main]
+async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+
+    let (tmp_db, storage): (_, Arc<dyn SolverStorage>) = if args.postgres {
+        let (db, client) = setup_mock_database().await;
+        (Some(db), Arc::new(client))
+    } else {
+        let storage = match args.storage_latency {
+            Some(latency) => MemoryStorage::with_latency(latency),
+            None => MemoryStorage::new(),
+        };
+        (None, Arc::new(storage))
+    };
+
+    let api_options = ApiOptions {
+        limits: args.limits,
+        ..Default::default()
+    };
+    let solver = MockSolver::init_with_options(
+        MockEvents::Manual,
+        tmp_db,
+        storage,
+        api_options,
+        args.auction_options,
+    )
+    .await;
+
+    let client = SolverClient::new(solver.solver_api());
+    client.connect(None).await;
+
+    let namespaces = args.workload.namespaces;
+    let mut workload = Workload::new(args.workload);
+    for registration in workload.registrations() {
+        client
+            .register_rollup(&registration)
+            .await
+            .context("failed to register rollup")?;
+    }
+
+    let mut latencies = Vec::new();
+    let mut finalizations = Vec::new();
+    let mut rejected = BTreeMap::<&'static str, usize>::new();
+    let mut submitting = Duration::ZERO;
+
+    for view in 1..=args.views {
+        // Bids are signed up front so signing does not count towards the latency
+        let bids = workload.bids(view);
+
+        // Rollups registered during the bids come after the ones the bids are for
+        let registration = args
+            .register_during_bids
+            .then(|| workload.registration(namespaces + view));
+
+        let start = Instant::now();
+        let submissions = stream::iter(&bids)
+            .map(|bid| {
+                let client = &client;
+                async move {
+                    let start = Instant::now();
+                    let result = client.submit_bid(bid).await;
+                    (start.elapsed(), result)
+                }
+            })
+            .buffer_unordered(args.concurrency.max(1))
+            .collect::<Vec<_>>();
+        let registering = async {
+            match &registration {
+                Some(registration) => client.register_rollup(registration).await.map(|_| ()),
+                None => Ok(()),
+            }
+        };
+        let (results, registered) = futures::join!(submissions, registering);
+        submitting += start.elapsed();
+        registered.context("failed to register rollup")?;
+
+        for (latency, result) in results {
+            latencies.push(latency);
+            if let Err(err) = result {
+                *rejected.entry(rejection_reason(&err)).or_default() += 1;
+            }
+        }
+
+        let start = Instant::now();
+        solver
+            .state()
+            .finalize_auction(ViewNumber::new(view))
+            .await
+            .context("failed to finalize auction")?;
+        finalizations.push(start.elapsed());
+    }
+
+    let submitted = latencies.len();
+    let rejections: usize = rejected.values().sum();
+    println!(
+        "submitted {submitted} bids over {} views: {} accepted, {rejections} rejected",
+        args.views,
+        submitted - rejections
+    );
+    println!(
+        "throughput: {:.1} bids/s{}",
+        submitted as f64 / submitting.as_secs_f64().max(f64::EPSILON),
+        if args.register_during_bids {
+            " while registering rollups"
+        } else {
+            ""
+        }
+    );
+    println!("submit latency: {}", percentiles(&mut latencies));
+    println!("finalize latency: {}", percentiles(&mut finalizations));
+    for (reason, count) in &rejected {
+        println!("rejected {reason}: {count}");
+    }
+
+    Ok(())
+}
+
+fn percentiles(samples: &mut [Duration]) -> String {
+    if samples.is_empty() {
+        return "no samples".to_string();
+    }
+
+    samples.sort();
+    let at = |p: f64| samples[((samples.len() - 1) as f64 * p).round() as usize];
+
+    format!(
+        "p50 {:?}, p90 {:?}, p99 {:?}, max {:?}",
+        at(0.5),
+        at(0.9),
+        at(0.99),
+        samples[samples.len() - 1]
+    )
+}
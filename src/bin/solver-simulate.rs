@@ -0,0 +1,103 @@
+//! Offline auction simulator.
+//!
+//! Replays recorded traffic view by view through the solver's auction code and writes one JSON
+//! report per view with the winners, the revenue and the namespaces that fell back to their
+//! reserve builder. Traffic is read either from a JSONL file of `TrafficRecord`s or from the
+//! `auction_results` table of a solver database, whose recorded inputs are solved again.
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    path::PathBuf,
+};
+
+use anyhow::{bail, Context};
+use clap::Parser;
+use espresso_types::FeeAmount;
+use marketplace_solver::{
+    simulation::{load_recorded_auctions, read_traffic, resolve_recorded, Simulation},
+    AuctionOptions, DatabaseOptions,
+};
+
+#[derive(Parser, Clone, Debug)]
+struct Args {
+    /// JSONL file of recorded traffic records
+    #[clap(long, env = "MARKETPLACE_SOLVER_SIMULATE_TRAFFIC")]
+    traffic: Option<PathBuf>,
+
+    /// Solve the auctions recorded in the solver database again instead of replaying a file
+    #[clap(long, default_value_t = false)]
+    from_database: bool,
+
+    /// Write the per-view reports to this file instead of stdout
+    #[clap(long)]
+    output: Option<PathBuf>,
+
+    #[clap(flatten)]
+    database_options: DatabaseOptions,
+
+    #[clap(flatten)]
+    auction_options: AuctionOptions,
+}
+
+#[async_std::main]
+async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+
+    let (reports, late_bids) = match (args.traffic, args.from_database) {
+        (Some(path), false) => {
+            let file =
+                File::open(&path).with_context(|| format!("failed to open {}", path.display()))?;
+
+            let mut simulation = Simulation::new(args.auction_options);
+            for record in read_traffic(BufReader::new(file))? {
+                simulation.apply(record);
+            }
+            let late_bids = simulation.late_bids();
+
+            (simulation.finish(), late_bids)
+        }
+        (None, true) => {
+            // The simulator only reads, it must not migrate a production database
+            let database_options = DatabaseOptions {
+                migrations: false,
+                ..args.database_options
+            };
+            let db = database_options.connect().await?;
+            let outcomes = load_recorded_auctions(&db).await?;
+            db.close().await;
+
+            (resolve_recorded(outcomes, &args.auction_options), 0)
+        }
+        _ => bail!("provide either a traffic file or --from-database"),
+    };
+
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => {
+            Box::new(BufWriter::new(File::create(path).with_context(|| {
+                format!("failed to create {}", path.display())
+            })?))
+        }
+        None => Box::new(io::stdout().lock()),
+    };
+    for report in &reports {
+        writeln!(out, "{}", serde_json::to_string(report)?)?;
+    }
+    out.flush()?;
+
+    let revenue = reports
+        .iter()
+        .try_fold(FeeAmount::from(0_u64), |sum, report| {
+            sum.checked_add(report.revenue)
+                .context("total revenue overflows")
+        })?;
+    let fallbacks: usize = reports.iter().map(|r| r.reserve_fallbacks.len()).sum();
+    let changed = reports.iter().filter(|r| r.changed == Some(true)).count();
+
+    eprintln!(
+        "simulated {} views: revenue {revenue}, {fallbacks} reserve fallbacks, {changed} changed \
+         results, {late_bids} late bids dropped",
+        reports.len()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,43 @@
+//! Offline verification of a finalized auction.
+//!
+//! Takes an `AuctionOutcome` as published by the solver's `auction_outcome` route, checks the
+//! commitment against the recorded bids and registrations and recomputes the results from them.
+use std::{fs, path::PathBuf};
+
+use anyhow::{bail, Context};
+use clap::Parser;
+use marketplace_solver::auction::AuctionOutcome;
+
+#[derive(Parser, Clone, Debug)]
+struct Args {
+    /// JSON file containing the auction outcome to verify
+    #[clap(long, env = "MARKETPLACE_SOLVER_VERIFY_OUTCOME")]
+    outcome: PathBuf,
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+
+    let bytes = fs::read(&args.outcome)
+        .with_context(|| format!("failed to read {}", args.outcome.display()))?;
+    let outcome: AuctionOutcome =
+        serde_json::from_slice(&bytes).context("failed to parse auction outcome")?;
+
+    if let Err(err) = outcome.verify() {
+        bail!(
+            "auction for view {:?} failed verification: {err}",
+            outcome.inputs.view_number
+        );
+    }
+
+    println!(
+        "auction for view {:?} verified: {} bids, {} registrations, inputs commitment {}",
+        outcome.inputs.view_number,
+        outcome.inputs.bids.len(),
+        outcome.inputs.registrations.len(),
+        outcome.commitment
+    );
+    println!("{}", serde_json::to_string_pretty(&outcome.results)?);
+
+    Ok(())
+}
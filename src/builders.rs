@@ -0,0 +1,62 @@
+use committable::{Commitment, Committable, RawCommitmentBuilder};
+use espresso_types::{EthKeyPair, FeeAccount};
+use hotshot_types::traits::signature_key::BuilderSignatureKey;
+use serde::{Deserialize, Serialize};
+use tide_disco::Url;
+
+pub type BuilderSignature = <FeeAccount as BuilderSignatureKey>::BuilderSignature;
+
+/// Metadata a builder publishes about itself.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuilderRegistrationBody {
+    /// Account the builder signs its bids with
+    pub account: FeeAccount,
+    pub url: Url,
+    /// Free-form contact information
+    pub text: String,
+}
+
+impl Committable for BuilderRegistrationBody {
+    fn commit(&self) -> Commitment<Self> {
+        RawCommitmentBuilder::new(&Self::tag())
+            .fixed_size_field("account", &self.account.to_fixed_bytes())
+            .var_size_field("url", self.url.as_str().as_bytes())
+            .var_size_field("text", self.text.as_bytes())
+            .finalize()
+    }
+
+    fn tag() -> String {
+        "BUILDER_REGISTRATION".to_string()
+    }
+}
+
+impl BuilderRegistrationBody {
+    /// Signs the registration with the key of `account`.
+    pub fn signed(
+        self,
+        key: &EthKeyPair,
+    ) -> Result<BuilderRegistration, <FeeAccount as BuilderSignatureKey>::SignError> {
+        let signature = FeeAccount::sign_builder_message(key, self.commit().as_ref())?;
+
+        Ok(BuilderRegistration {
+            body: self,
+            signature,
+        })
+    }
+}
+
+/// Builder metadata signed by the builder's account.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuilderRegistration {
+    pub body: BuilderRegistrationBody,
+    pub signature: BuilderSignature,
+}
+
+impl BuilderRegistration {
+    /// Checks that the registration was signed by the account it registers.
+    pub fn verify(&self) -> bool {
+        self.body
+            .account
+            .validate_builder_signature(&self.signature, self.body.commit().as_ref())
+    }
+}
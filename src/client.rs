@@ -0,0 +1,194 @@
+use std::time::Duration;
+
+use committable::Committable;
+use espresso_types::{
+    v0_3::{BidTx, RollupRegistration, RollupRegistrationBody, RollupUpdate, RollupUpdatebody},
+    FeeAccount, PubKey, SeqTypes,
+};
+use futures::stream::{BoxStream, StreamExt};
+use hotshot::types::SignatureKey;
+use hotshot_types::{
+    data::ViewNumber,
+    signature_key::BLSPrivKey,
+    traits::node_implementation::{ConsensusTime, NodeType},
+};
+use surf_disco::Url;
+
+use crate::{
+    auction::AuctionOutcome,
+    builders::BuilderRegistration,
+    feeds::{FinalizedAuction, RegistrationChange},
+    signing::SignedAuctionResults,
+    state::ResultsRequest,
+    SolverError, SolverResult,
+};
+
+type Ver = <SeqTypes as NodeType>::Base;
+
+/// Typed client for the solver API.
+#[derive(Clone)]
+pub struct SolverClient {
+    inner: surf_disco::Client<SolverError, Ver>,
+}
+
+impl SolverClient {
+    /// Creates a client for the solver API served at `url`, e.g. `http://localhost:7777/solver_api`.
+    pub fn new(url: Url) -> Self {
+        Self {
+            inner: surf_disco::Client::new(url),
+        }
+    }
+
+    /// Waits until the server is reachable, for at most `timeout` if given.
+    pub async fn connect(&self, timeout: Option<Duration>) -> bool {
+        self.inner.connect(timeout).await
+    }
+
+    pub async fn submit_bid(&self, bid: &BidTx) -> SolverResult<()> {
+        self.inner.post("submit_bid").body_json(bid)?.send().await
+    }
+
+    /// Results of a finalized auction.
+    pub async fn auction_results(&self, view: ViewNumber) -> SolverResult<SignedAuctionResults> {
+        self.inner
+            .get(&format!("auction_results/{}", view.u64()))
+            .send()
+            .await
+    }
+
+    /// Current results of an auction, which need not be finalized yet, for the leader of its
+    /// view with `leader_key`.
+    pub async fn auction_results_permissioned(
+        &self,
+        view: ViewNumber,
+        leader_key: &BLSPrivKey,
+    ) -> SolverResult<SignedAuctionResults> {
+        self.inner
+            .post("auction_results_permissioned")
+            .body_json(&ResultsRequest::new(view.u64(), leader_key)?)?
+            .send()
+            .await
+    }
+
+    /// Inputs and results of a finalized auction, which can be verified offline.
+    pub async fn auction_outcome(&self, view: ViewNumber) -> SolverResult<AuctionOutcome> {
+        self.inner
+            .get(&format!("auction_outcome/{}", view.u64()))
+            .send()
+            .await
+    }
+
+    pub async fn solver_key(&self) -> SolverResult<PubKey> {
+        self.inner.get("solver_key").send().await
+    }
+
+    pub async fn register_rollup(
+        &self,
+        registration: &RollupRegistration,
+    ) -> SolverResult<RollupRegistration> {
+        self.inner
+            .post("register_rollup")
+            .body_json(registration)?
+            .send()
+            .await
+    }
+
+    pub async fn update_rollup(&self, update: &RollupUpdate) -> SolverResult<RollupRegistration> {
+        self.inner
+            .post("update_rollup")
+            .body_json(update)?
+            .send()
+            .await
+    }
+
+    pub async fn rollup_registrations(&self) -> SolverResult<Vec<RollupRegistration>> {
+        self.inner.get("rollup_registrations").send().await
+    }
+
+    pub async fn register_builder(
+        &self,
+        registration: &BuilderRegistration,
+    ) -> SolverResult<BuilderRegistration> {
+        self.inner
+            .post("register_builder")
+            .body_json(registration)?
+            .send()
+            .await
+    }
+
+    pub async fn builder_registration(
+        &self,
+        account: FeeAccount,
+    ) -> SolverResult<BuilderRegistration> {
+        self.inner
+            .get(&format!("builder_registration/{account}"))
+            .send()
+            .await
+    }
+
+    pub async fn builder_registrations(&self) -> SolverResult<Vec<BuilderRegistration>> {
+        self.inner.get("builder_registrations").send().await
+    }
+
+    /// Registrations of the builders that won the finalized auction for `view`.
+    pub async fn auction_builders(
+        &self,
+        view: ViewNumber,
+    ) -> SolverResult<Vec<BuilderRegistration>> {
+        self.inner
+            .get(&format!("auction_builders/{}", view.u64()))
+            .send()
+            .await
+    }
+
+    /// Streams the results of every auction finalized after subscribing.
+    pub async fn subscribe_auction_results(
+        &self,
+        include_bid_counts: bool,
+    ) -> SolverResult<BoxStream<'static, SolverResult<FinalizedAuction>>> {
+        let stream = self
+            .inner
+            .socket(&format!("auction_results_stream/{include_bid_counts}"))
+            .subscribe::<FinalizedAuction>()
+            .await?;
+
+        Ok(stream.boxed())
+    }
+
+    /// Streams rollup registration changes, starting after `cursor` if given.
+    pub async fn subscribe_registration_changes(
+        &self,
+        cursor: Option<u64>,
+    ) -> SolverResult<BoxStream<'static, SolverResult<RegistrationChange>>> {
+        let path = match cursor {
+            Some(cursor) => format!("registration_changes/{cursor}"),
+            None => "registration_changes".to_string(),
+        };
+        let stream = self
+            .inner
+            .socket(&path)
+            .subscribe::<RegistrationChange>()
+            .await?;
+
+        Ok(stream.boxed())
+    }
+}
+
+/// Signs a rollup registration with `private_key`, which must belong to `body.signature_key`.
+pub fn sign_registration(
+    body: RollupRegistrationBody,
+    private_key: &BLSPrivKey,
+) -> SolverResult<RollupRegistration> {
+    let signature = <SeqTypes as NodeType>::SignatureKey::sign(private_key, body.commit().as_ref())
+        .map_err(|err| SolverError::InvalidSignature(err.to_string()))?;
+
+    Ok(RollupRegistration { body, signature })
+}
+
+/// Signs a rollup update with `private_key`, which must belong to `body.signature_key`.
+pub fn sign_update(body: RollupUpdatebody, private_key: &BLSPrivKey) -> SolverResult<RollupUpdate> {
+    let signature = <SeqTypes as NodeType>::SignatureKey::sign(private_key, body.commit().as_ref())
+        .map_err(|err| SolverError::InvalidSignature(err.to_string()))?;
+
+    Ok(RollupUpdate { body, signature })
+}
@@ -1,12 +1,29 @@
 use anyhow::Context;
+use async_trait::async_trait;
+use committable::Committable;
+use espresso_types::{
+    v0_3::{BidTx, RollupRegistration},
+    FeeAccount, NamespaceId,
+};
+use hotshot_types::{data::ViewNumber, traits::node_implementation::ConsensusTime};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use sqlx::{
     pool::PoolConnection,
     postgres::{PgConnectOptions, PgPoolOptions, PgSslMode},
-    ConnectOptions, Error, PgPool, Postgres,
+    ConnectOptions, Error, FromRow, PgPool, Postgres,
 };
 use tide_disco::Url;
 
-use crate::{DatabaseOptions, SolverError};
+use crate::{
+    admin::AuditLogEntry,
+    auction::AuctionOutcome,
+    builders::BuilderRegistration,
+    feeds::{RegistrationChange, RegistrationChangeKind},
+    overflow_err, serde_json_err,
+    storage::{ConnectionStats, SolverStorage},
+    DatabaseOptions, SolverError, SolverResult,
+};
 
 // PgPool is wrapped in an Arc internally so cloning here increments the reference count
 #[derive(Clone)]
@@ -83,6 +100,11 @@ impl PostgresClient {
         &self.0
     }
 
+    /// Closes the pool, waiting for checked out connections to be returned.
+    pub async fn close(&self) {
+        self.0.close().await
+    }
+
     pub async fn acquire(&self) -> Result<PoolConnection<Postgres>, Error> {
         self.0.acquire().await
     }
@@ -94,19 +116,595 @@ impl From<sqlx::Error> for SolverError {
     }
 }
 
+fn namespace_key(namespace_id: NamespaceId) -> SolverResult<i64> {
+    u64::from(namespace_id).try_into().map_err(overflow_err)
+}
+
+fn view_key(view_number: ViewNumber) -> SolverResult<i64> {
+    view_number.u64().try_into().map_err(overflow_err)
+}
+
+#[async_trait]
+impl SolverStorage for PostgresClient {
+    async fn write_registration_change(
+        &self,
+        mut change: RegistrationChange,
+    ) -> SolverResult<RegistrationChange> {
+        let namespace_id = change.namespace_id;
+        let data = change
+            .registration
+            .as_ref()
+            .map(serde_json::to_value)
+            .transpose()
+            .map_err(serde_json_err)?;
+
+        // Dropping the transaction on an early return rolls it back
+        let mut tx = self.pool().begin().await?;
+
+        let written = match (&data, change.kind) {
+            (Some(data), RegistrationChangeKind::Registered) => {
+                sqlx::query(
+                    "INSERT INTO rollup_registrations VALUES ($1, $2) ON CONFLICT DO NOTHING;",
+                )
+                .bind(namespace_key(namespace_id)?)
+                .bind(data)
+                .execute(&mut *tx)
+                .await?
+            }
+            (Some(data), _) => {
+                sqlx::query(
+                    "INSERT INTO rollup_registrations VALUES ($1, $2) \
+                     ON CONFLICT (namespace_id) DO UPDATE SET data = excluded.data;",
+                )
+                .bind(namespace_key(namespace_id)?)
+                .bind(data)
+                .execute(&mut *tx)
+                .await?
+            }
+            (None, _) => {
+                sqlx::query("DELETE FROM rollup_registrations WHERE namespace_id = $1;")
+                    .bind(namespace_key(namespace_id)?)
+                    .execute(&mut *tx)
+                    .await?
+            }
+        };
+
+        if written.rows_affected() == 0 {
+            return Err(match data {
+                Some(_) => SolverError::RollupAlreadyExists(namespace_id),
+                None => SolverError::RollupNotFound(namespace_id),
+            });
+        }
+
+        let cursor: i64 = sqlx::query_scalar(
+            "INSERT INTO rollup_registration_changes \
+             (namespace_id, kind, registration, view_number, changed_at) \
+             VALUES ($1, $2, $3, $4, $5) RETURNING cursor;",
+        )
+        .bind(namespace_key(namespace_id)?)
+        .bind(change.kind.as_str())
+        .bind(&data)
+        .bind(
+            change
+                .view_number
+                .map(i64::try_from)
+                .transpose()
+                .map_err(overflow_err)?,
+        )
+        .bind::<i64>(change.timestamp.try_into().map_err(overflow_err)?)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        change.cursor = cursor.try_into().map_err(overflow_err)?;
+        Ok(change)
+    }
+
+    async fn get_rollup_registration(
+        &self,
+        namespace_id: NamespaceId,
+    ) -> SolverResult<Option<RollupRegistration>> {
+        let result: Option<RollupRegistrationResult> =
+            sqlx::query_as("SELECT * from rollup_registrations where namespace_id = $1;")
+                .bind(namespace_key(namespace_id)?)
+                .fetch_optional(self.pool())
+                .await?;
+
+        result
+            .map(|r| serde_json::from_value(r.data).map_err(serde_json_err))
+            .transpose()
+    }
+
+    async fn get_all_rollup_registrations(&self) -> SolverResult<Vec<RollupRegistration>> {
+        let rows: Vec<RollupRegistrationResult> =
+            sqlx::query_as("SELECT * from rollup_registrations;")
+                .fetch_all(self.pool())
+                .await?;
+
+        rows.into_iter()
+            .map(|r| serde_json::from_value(r.data).map_err(serde_json_err))
+            .collect()
+    }
+
+    async fn get_registration_changes(&self, cursor: u64) -> SolverResult<Vec<RegistrationChange>> {
+        let rows: Vec<RegistrationChangeResult> = sqlx::query_as(
+            "SELECT * from rollup_registration_changes WHERE cursor > $1 ORDER BY cursor;",
+        )
+        .bind::<i64>(cursor.try_into().map_err(overflow_err)?)
+        .fetch_all(self.pool())
+        .await?;
+
+        rows.into_iter().map(RegistrationChange::try_from).collect()
+    }
+
+    async fn put_builder_registration(
+        &self,
+        registration: &BuilderRegistration,
+    ) -> SolverResult<()> {
+        let json = serde_json::to_value(registration).map_err(serde_json_err)?;
+
+        sqlx::query(
+            "INSERT INTO builder_registrations VALUES ($1, $2) \
+             ON CONFLICT (account) DO UPDATE SET data = excluded.data;",
+        )
+        .bind(registration.body.account.to_string())
+        .bind(&json)
+        .execute(self.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_builder_registration(
+        &self,
+        account: FeeAccount,
+    ) -> SolverResult<Option<BuilderRegistration>> {
+        let result: Option<BuilderRegistrationResult> =
+            sqlx::query_as("SELECT * from builder_registrations where account = $1;")
+                .bind(account.to_string())
+                .fetch_optional(self.pool())
+                .await?;
+
+        result
+            .map(|r| serde_json::from_value(r.data).map_err(serde_json_err))
+            .transpose()
+    }
+
+    async fn get_all_builder_registrations(&self) -> SolverResult<Vec<BuilderRegistration>> {
+        let rows: Vec<BuilderRegistrationResult> =
+            sqlx::query_as("SELECT * from builder_registrations;")
+                .fetch_all(self.pool())
+                .await?;
+
+        rows.into_iter()
+            .map(|r| serde_json::from_value(r.data).map_err(serde_json_err))
+            .collect()
+    }
+
+    async fn ban_builder(&self, account: FeeAccount) -> SolverResult<()> {
+        sqlx::query("INSERT INTO builder_bans VALUES ($1) ON CONFLICT DO NOTHING;")
+            .bind(account.to_string())
+            .execute(self.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    async fn unban_builder(&self, account: FeeAccount) -> SolverResult<()> {
+        sqlx::query("DELETE FROM builder_bans WHERE account = $1;")
+            .bind(account.to_string())
+            .execute(self.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    async fn builder_banned(&self, account: FeeAccount) -> SolverResult<bool> {
+        Ok(
+            sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM builder_bans WHERE account = $1);")
+                .bind(account.to_string())
+                .fetch_one(self.pool())
+                .await?,
+        )
+    }
+
+    async fn get_banned_builders(&self) -> SolverResult<Vec<FeeAccount>> {
+        let accounts: Vec<String> = sqlx::query_scalar("SELECT account FROM builder_bans;")
+            .fetch_all(self.pool())
+            .await?;
+
+        accounts
+            .into_iter()
+            .map(|account| {
+                account.parse().map_err(|err| {
+                    SolverError::Database(format!("invalid banned account {account}: {err}"))
+                })
+            })
+            .collect()
+    }
+
+    async fn put_bid(&self, bid: &BidTx) -> SolverResult<()> {
+        let json = serde_json::to_value(bid).map_err(serde_json_err)?;
+
+        sqlx::query("INSERT INTO bids VALUES ($1, $2, $3) ON CONFLICT DO NOTHING;")
+            .bind(view_key(bid.view())?)
+            .bind(bid.commit().to_string())
+            .bind(&json)
+            .execute(self.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_bids(&self, view_number: ViewNumber) -> SolverResult<Vec<BidTx>> {
+        let rows: Vec<Value> = sqlx::query_scalar("SELECT data FROM bids WHERE view_number = $1;")
+            .bind(view_key(view_number)?)
+            .fetch_all(self.pool())
+            .await?;
+
+        rows.into_iter()
+            .map(|data| serde_json::from_value(data).map_err(serde_json_err))
+            .collect()
+    }
+
+    async fn get_bids_from(&self, view_number: ViewNumber) -> SolverResult<Vec<BidTx>> {
+        let rows: Vec<Value> = sqlx::query_scalar(
+            "SELECT data FROM bids WHERE view_number >= $1 ORDER BY view_number;",
+        )
+        .bind(view_key(view_number)?)
+        .fetch_all(self.pool())
+        .await?;
+
+        rows.into_iter()
+            .map(|data| serde_json::from_value(data).map_err(serde_json_err))
+            .collect()
+    }
+
+    async fn delete_bids(&self, view_number: ViewNumber) -> SolverResult<()> {
+        sqlx::query("DELETE FROM bids WHERE view_number <= $1;")
+            .bind(view_key(view_number)?)
+            .execute(self.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    async fn insert_auction_outcome(&self, outcome: &AuctionOutcome) -> SolverResult<()> {
+        let json = serde_json::to_value(outcome).map_err(serde_json_err)?;
+
+        sqlx::query(
+            "INSERT INTO auction_results VALUES ($1, $2, $3) ON CONFLICT (view_number) DO NOTHING;",
+        )
+        .bind(view_key(outcome.inputs.view_number)?)
+        .bind(outcome.commitment.to_string())
+        .bind(&json)
+        .execute(self.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    async fn replace_auction_outcome(&self, outcome: &AuctionOutcome) -> SolverResult<()> {
+        let json = serde_json::to_value(outcome).map_err(serde_json_err)?;
+
+        sqlx::query(
+            "UPDATE auction_results SET inputs_commitment = $2, data = $3 \
+             WHERE view_number = $1;",
+        )
+        .bind(view_key(outcome.inputs.view_number)?)
+        .bind(outcome.commitment.to_string())
+        .bind(&json)
+        .execute(self.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_auction_outcome(
+        &self,
+        view_number: ViewNumber,
+    ) -> SolverResult<Option<AuctionOutcome>> {
+        let result: Option<AuctionOutcomeResult> =
+            sqlx::query_as("SELECT * from auction_results where view_number = $1;")
+                .bind(view_key(view_number)?)
+                .fetch_optional(self.pool())
+                .await?;
+
+        result
+            .map(|r| serde_json::from_value(r.data).map_err(serde_json_err))
+            .transpose()
+    }
+
+    async fn get_all_auction_outcomes(&self) -> SolverResult<Vec<AuctionOutcome>> {
+        let rows: Vec<AuctionOutcomeResult> =
+            sqlx::query_as("SELECT * from auction_results ORDER BY view_number;")
+                .fetch_all(self.pool())
+                .await?;
+
+        rows.into_iter()
+            .map(|r| serde_json::from_value(r.data).map_err(serde_json_err))
+            .collect()
+    }
+
+    async fn admin_request_executed(&self, request_commitment: &str) -> SolverResult<bool> {
+        Ok(sqlx::query_scalar(
+            "SELECT EXISTS(SELECT 1 FROM admin_audit_log WHERE request_commitment = $1);",
+        )
+        .bind(request_commitment)
+        .fetch_one(self.pool())
+        .await?)
+    }
+
+    async fn append_audit_entry(&self, mut entry: AuditLogEntry) -> SolverResult<AuditLogEntry> {
+        let action = serde_json::to_value(&entry.action).map_err(serde_json_err)?;
+
+        entry.id = sqlx::query_scalar(
+            "INSERT INTO admin_audit_log \
+             (operator, request_commitment, action, error, executed_at) \
+             VALUES ($1, $2, $3, $4, $5) RETURNING id;",
+        )
+        .bind(&entry.operator)
+        .bind(&entry.request_commitment)
+        .bind(&action)
+        .bind(&entry.error)
+        .bind(entry.executed_at)
+        .fetch_one(self.pool())
+        .await?;
+
+        Ok(entry)
+    }
+
+    async fn get_audit_log(&self) -> SolverResult<Vec<AuditLogEntry>> {
+        let rows: Vec<AuditLogResult> =
+            sqlx::query_as("SELECT * from admin_audit_log ORDER BY id;")
+                .fetch_all(self.pool())
+                .await?;
+
+        rows.into_iter()
+            .map(|r| {
+                Ok(AuditLogEntry {
+                    id: r.id,
+                    operator: r.operator,
+                    request_commitment: r.request_commitment,
+                    action: serde_json::from_value(r.action).map_err(serde_json_err)?,
+                    error: r.error,
+                    executed_at: r.executed_at,
+                })
+            })
+            .collect()
+    }
+
+    async fn set_bidding_paused(&self, paused: bool) -> SolverResult<()> {
+        let query = if paused {
+            "INSERT INTO bidding_paused VALUES (TRUE) ON CONFLICT DO NOTHING;"
+        } else {
+            "DELETE FROM bidding_paused;"
+        };
+        sqlx::query(query).execute(self.pool()).await?;
+
+        Ok(())
+    }
+
+    async fn bidding_paused(&self) -> SolverResult<bool> {
+        Ok(
+            sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM bidding_paused);")
+                .fetch_one(self.pool())
+                .await?,
+        )
+    }
+
+    async fn ping(&self) -> SolverResult<()> {
+        sqlx::query("SELECT 1;").execute(self.pool()).await?;
+        Ok(())
+    }
+
+    async fn close(&self) {
+        PostgresClient::close(self).await
+    }
+
+    fn is_closed(&self) -> bool {
+        self.0.is_closed()
+    }
+
+    fn connection_stats(&self) -> Option<ConnectionStats> {
+        Some(ConnectionStats {
+            connections: self.0.size(),
+            idle: self.0.num_idle(),
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, FromRow)]
+struct RollupRegistrationResult {
+    namespace_id: i64,
+    data: Value,
+}
+
+#[derive(Debug, Deserialize, Serialize, FromRow)]
+struct RegistrationChangeResult {
+    cursor: i64,
+    namespace_id: i64,
+    kind: String,
+    registration: Option<Value>,
+    view_number: Option<i64>,
+    changed_at: i64,
+}
+
+impl TryFrom<RegistrationChangeResult> for RegistrationChange {
+    type Error = SolverError;
+
+    fn try_from(r: RegistrationChangeResult) -> SolverResult<Self> {
+        Ok(Self {
+            cursor: r.cursor.try_into().map_err(overflow_err)?,
+            namespace_id: u64::try_from(r.namespace_id).map_err(overflow_err)?.into(),
+            kind: r.kind.parse()?,
+            registration: r
+                .registration
+                .map(serde_json::from_value)
+                .transpose()
+                .map_err(serde_json_err)?,
+            view_number: r
+                .view_number
+                .map(u64::try_from)
+                .transpose()
+                .map_err(overflow_err)?,
+            timestamp: r.changed_at.try_into().map_err(overflow_err)?,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, FromRow)]
+struct AuditLogResult {
+    id: i64,
+    operator: String,
+    request_commitment: String,
+    action: Value,
+    error: Option<String>,
+    executed_at: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize, FromRow)]
+struct BuilderRegistrationResult {
+    account: String,
+    data: Value,
+}
+
+#[derive(Debug, Deserialize, Serialize, FromRow)]
+struct AuctionOutcomeResult {
+    view_number: i64,
+    inputs_commitment: String,
+    data: Value,
+}
+
 #[cfg(all(any(test, feature = "testing"), not(target_os = "windows")))]
 pub mod mock {
+    use std::{
+        env, fs,
+        os::unix::fs::MetadataExt,
+        path::PathBuf,
+        process::{Child, Command, Stdio},
+        time::Duration,
+    };
+
+    use anyhow::{bail, ensure, Context};
+    use async_std::task::{block_on, sleep};
     use hotshot_query_service::data_source::sql::testing::TmpDb;
+    use portpicker::pick_unused_port;
+    use sqlx::{Connection, PgConnection};
+    use tempfile::TempDir;
+    use tide_disco::Url;
 
     use super::PostgresClient;
     use crate::DatabaseOptions;
 
-    pub async fn setup_mock_database() -> (TmpDb, PostgresClient) {
-        let db: TmpDb = TmpDb::init().await;
-        let host = db.host();
-        let port = db.port();
+    /// Selects the Postgres server tests run against, see [`TestDatabase`]
+    pub const TEST_POSTGRES_ENV: &str = "MARKETPLACE_SOLVER_TEST_POSTGRES";
+    /// Directory containing the `initdb` and `postgres` binaries, if they are not on the `PATH`
+    pub const TEST_POSTGRES_BIN_ENV: &str = "MARKETPLACE_SOLVER_TEST_POSTGRES_BIN";
+
+    /// How long to wait for a freshly started server to accept connections
+    const STARTUP_RETRIES: usize = 100;
+    const STARTUP_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+    /// A Postgres database that lives as long as a test.
+    ///
+    /// The server is chosen with `MARKETPLACE_SOLVER_TEST_POSTGRES`:
+    /// - unset or `docker`: a container started with `TmpDb`
+    /// - `local`: a server process started from the `initdb` and `postgres` binaries, which
+    ///   needs neither Docker nor network access
+    /// - a `postgres://` URL: a new database on an existing server, dropped afterwards
+    pub enum TestDatabase {
+        Docker(TmpDb),
+        Local(LocalPostgres),
+        Existing { server: Url, name: String },
+    }
+
+    impl Drop for TestDatabase {
+        fn drop(&mut self) {
+            if let Self::Existing { server, name } = self {
+                let result = block_on(async {
+                    let mut connection = PgConnection::connect(server.as_str()).await?;
+                    sqlx::query(&format!("DROP DATABASE IF EXISTS {name} WITH (FORCE);"))
+                        .execute(&mut connection)
+                        .await
+                });
+                if let Err(err) = result {
+                    tracing::warn!("failed to drop test database {name}: {err}");
+                }
+            }
+        }
+    }
+
+    /// A Postgres server process with its data in a temporary directory.
+    ///
+    /// Postgres refuses to run as root, so starting one fails if the tests run as root.
+    pub struct LocalPostgres {
+        port: u16,
+        server: Child,
+        // Removed when dropped, after the server is stopped
+        dir: TempDir,
+    }
+
+    impl LocalPostgres {
+        pub fn start() -> anyhow::Result<Self> {
+            let bin = env::var_os(TEST_POSTGRES_BIN_ENV).map(PathBuf::from);
+            let command = |name: &str| match &bin {
+                Some(dir) => Command::new(dir.join(name)),
+                None => Command::new(name),
+            };
+
+            let dir = tempfile::Builder::new()
+                .prefix("marketplace-solver-postgres-")
+                .tempdir()
+                .context("failed to create data directory")?;
+            // The directory belongs to the effective user of the process
+            if fs::metadata(dir.path())?.uid() == 0 {
+                bail!(
+                    "postgres does not run as root: run the tests as another user, or set \
+                     {TEST_POSTGRES_ENV} to `docker` or a server URL"
+                );
+            }
+
+            let port = pick_unused_port().context("no free port")?;
+            let status = command("initdb")
+                .arg("-D")
+                .arg(dir.path())
+                .args(["-U", "postgres", "--auth=trust"])
+                .stdout(Stdio::null())
+                .status()
+                .context("failed to run initdb")?;
+            ensure!(status.success(), "initdb failed with {status}");
+
+            let server = command("postgres")
+                .arg("-D")
+                .arg(dir.path())
+                .args(["-p", &port.to_string(), "-c", "listen_addresses=localhost"])
+                // Keep the Unix socket out of the system directory, which may not be writable
+                .arg("-k")
+                .arg(dir.path())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+                .context("failed to start postgres")?;
+
+            Ok(Self { port, server, dir })
+        }
+
+        pub fn port(&self) -> u16 {
+            self.port
+        }
+    }
+
+    impl Drop for LocalPostgres {
+        fn drop(&mut self) {
+            let _ = self.server.kill();
+            let _ = self.server.wait();
+        }
+    }
 
-        let opts = DatabaseOptions {
+    fn test_options(host: String, port: u16) -> DatabaseOptions {
+        DatabaseOptions {
             url: None,
             host: Some(host),
             port: Some(port),
@@ -117,16 +715,64 @@ pub mod mock {
             acquire_timeout: None,
             require_ssl: false,
             migrations: true,
-        };
+        }
+    }
 
-        // TmpDb will be dropped, which will cause the Docker container to be killed.
-        // Therefore, it is returned and kept in scope until needed.
-        (
-            db,
-            PostgresClient::connect(opts)
-                .await
-                .expect("failed to connect to database"),
-        )
+    /// Connects, retrying while the server is still starting up.
+    async fn connect(opts: DatabaseOptions) -> PostgresClient {
+        for _ in 0..STARTUP_RETRIES {
+            match PostgresClient::connect(opts.clone()).await {
+                Ok(client) => return client,
+                Err(err) => tracing::info!("waiting for test database: {err:#}"),
+            }
+            sleep(STARTUP_RETRY_DELAY).await;
+        }
+
+        PostgresClient::connect(opts)
+            .await
+            .expect("failed to connect to database")
+    }
+
+    pub async fn setup_mock_database() -> (TestDatabase, PostgresClient) {
+        match env::var(TEST_POSTGRES_ENV).ok().as_deref() {
+            None | Some("docker") => {
+                let db: TmpDb = TmpDb::init().await;
+                let opts = test_options(db.host(), db.port());
+
+                // TmpDb will be dropped, which will cause the Docker container to be killed.
+                // Therefore, it is returned and kept in scope until needed.
+                (TestDatabase::Docker(db), connect(opts).await)
+            }
+            Some("local") => {
+                let db = LocalPostgres::start().expect("failed to start local postgres");
+                let opts = test_options("localhost".to_string(), db.port());
+
+                (TestDatabase::Local(db), connect(opts).await)
+            }
+            Some(url) => {
+                let server: Url = url.parse().expect("invalid test database url");
+                let name = format!("solver_test_{}", rand::random::<u32>());
+
+                let mut connection = PgConnection::connect(server.as_str())
+                    .await
+                    .expect("failed to connect to test database server");
+                sqlx::query(&format!("CREATE DATABASE {name};"))
+                    .execute(&mut connection)
+                    .await
+                    .expect("failed to create test database");
+
+                let mut db_url = server.clone();
+                db_url.set_path(&name);
+                let opts = DatabaseOptions {
+                    url: Some(db_url.to_string()),
+                    host: None,
+                    port: None,
+                    ..test_options(String::new(), 0)
+                };
+
+                (TestDatabase::Existing { server, name }, connect(opts).await)
+            }
+        }
     }
 }
 
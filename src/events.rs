@@ -1,16 +1,23 @@
-use std::{pin::Pin, sync::Arc};
+use std::{
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
 use anyhow::Context;
-use async_std::sync::RwLock;
+use async_std::task::sleep;
 use espresso_types::SeqTypes;
 use futures::{Stream, StreamExt as _};
 use hotshot::types::Event;
 use hotshot_events_service::{events, events_source::StartupInfo};
-use hotshot_types::traits::node_implementation::NodeType;
+use hotshot_types::traits::node_implementation::{ConsensusTime, NodeType};
 use surf_disco::Client;
 use tide_disco::Url;
 
-use crate::state::GlobalState;
+use crate::state::{GlobalState, UpdateSolverState};
 
 pub struct EventsServiceClient(Client<events::Error, <SeqTypes as NodeType>::Base>);
 
@@ -44,20 +51,127 @@ impl EventsServiceClient {
     }
 }
 
+/// Delay before resubscribing to the events service after the event stream ended
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// State of the event handling task, shared with the status API.
+#[derive(Debug, Default)]
+pub struct EventsHealth {
+    running: AtomicBool,
+    connected: AtomicBool,
+    // Highest view number seen in any event, `0` until the first event arrives
+    latest_view: AtomicU64,
+}
+
+impl EventsHealth {
+    /// Whether the event handling task is still running.
+    pub fn running(&self) -> bool {
+        self.running.load(Ordering::Relaxed)
+    }
+
+    /// Whether the solver is currently subscribed to the events service.
+    pub fn connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    /// Highest view number seen from the events service.
+    pub fn latest_view(&self) -> Option<u64> {
+        Some(self.latest_view.load(Ordering::Relaxed)).filter(|view| *view > 0)
+    }
+
+    fn observe_view(&self, view: u64) {
+        self.latest_view.fetch_max(view, Ordering::Relaxed);
+    }
+}
+
+/// Marks the event handling task as stopped when dropped, including when the task panics.
+struct RunningGuard(Arc<EventsHealth>);
+
+impl RunningGuard {
+    fn new(health: Arc<EventsHealth>) -> Self {
+        health.running.store(true, Ordering::Relaxed);
+        Self(health)
+    }
+}
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        self.0.running.store(false, Ordering::Relaxed);
+        self.0.connected.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Handles events from the events service at `url`, resubscribing whenever the event stream
+/// ends or fails.
+pub async fn handle_events_with_reconnect(url: Url, state: Arc<GlobalState>) {
+    let _running = RunningGuard::new(state.events_health());
+
+    loop {
+        let client = EventsServiceClient::new(url.clone()).await;
+
+        match client.get_event_stream().await {
+            Ok(stream) => match handle_events(stream, state.clone()).await {
+                Ok(()) => tracing::warn!("event stream ended"),
+                Err(err) => tracing::error!("event stream failed: {err:#}"),
+            },
+            Err(err) => tracing::error!("failed to subscribe to events: {err:#}"),
+        }
+
+        sleep(RECONNECT_DELAY).await;
+
+        tracing::info!("reconnecting to events service");
+        state.metrics().event_stream_reconnects.inc();
+    }
+}
+
 pub async fn handle_events(
     mut stream: Pin<Box<dyn Stream<Item = Result<Event<SeqTypes>, events::Error>> + Send>>,
-    _state: Arc<RwLock<GlobalState>>,
+    state: Arc<GlobalState>,
 ) -> anyhow::Result<()> {
+    let health = state.events_health();
+    health.connected.store(true, Ordering::Relaxed);
+
+    let result = handle_event_stream(&mut stream, &state, &health).await;
+
+    health.connected.store(false, Ordering::Relaxed);
+    result
+}
+
+async fn handle_event_stream(
+    stream: &mut Pin<Box<dyn Stream<Item = Result<Event<SeqTypes>, events::Error>> + Send>>,
+    state: &GlobalState,
+    health: &EventsHealth,
+) -> anyhow::Result<()> {
+    let mut first = true;
     while let Some(event) = stream.next().await {
         let event = event?;
 
         tracing::info!("received event {:?}", event.event);
 
+        health.observe_view(event.view_number.u64());
+
+        // The first event of a subscription carries the current view of the network, which
+        // bidding starts from. Later views are opened as their predecessors finish.
+        if std::mem::take(&mut first) {
+            tracing::info!("opening bidding from view {:?}", event.view_number);
+            state.open_bidding_from(event.view_number).await?;
+        }
+
         // TODO ED: Remove this lint later
         #[allow(clippy::single_match)]
         match event.event {
             hotshot::types::EventType::ViewFinished { view_number } => {
-                tracing::info!("received view finished event {view_number:?}")
+                tracing::info!("received view finished event {view_number:?}");
+
+                state
+                    .metrics()
+                    .last_view_finished
+                    .set(view_number.u64() as i64);
+
+                // Bidding for the view is over, so its auction can be finalized
+                if let Err(err) = state.finalize_auction(view_number).await {
+                    tracing::error!("failed to finalize auction for view {view_number:?}: {err}");
+                }
             }
             _ => (),
         }
@@ -68,21 +182,30 @@ pub async fn handle_events(
 
 #[cfg(any(test, feature = "testing"))]
 pub mod mock {
-    use std::{sync::Arc, time::Duration};
+    use std::{
+        fs::File,
+        path::Path,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
 
     use async_compatibility_layer::art::async_spawn;
     use async_std::{
-        sync::RwLock,
+        channel::Receiver,
+        sync::{Mutex, RwLock},
         task::{sleep, JoinHandle},
     };
     use espresso_types::SeqTypes;
     use hotshot::rand::{self};
-    use hotshot_events_service::events_source::{EventConsumer, EventsStreamer};
+    use hotshot_events_service::events_source::{EventConsumer, EventsStreamer, StartupInfo};
     use hotshot_types::{
         data::ViewNumber,
         event::{Event, EventType},
         light_client::StateKeyPair,
-        signature_key::BLSPubKey,
+        signature_key::{BLSPrivKey, BLSPubKey},
         traits::{node_implementation::ConsensusTime, signature_key::SignatureKey},
         PeerConfig,
     };
@@ -91,17 +214,66 @@ pub mod mock {
     use tide_disco::{App, Url};
     use vbs::version::{StaticVersion, StaticVersionType};
 
+    use crate::recording::{read_recording, RecordedEvent};
+
     const NON_STAKED_NODE_COUNT: usize = 10;
     const NODE_STAKE: u64 = 1;
-    const STAKED_NODES: usize = 10;
+    pub const STAKED_NODES: usize = 10;
     pub type StaticVer01 = StaticVersion<0, 1>;
 
+    /// Private key of the node at `index` in the stake table of the mock events service, so that
+    /// tests can sign as the leader of a view.
+    pub fn staked_node_key(index: u64) -> BLSPrivKey {
+        BLSPubKey::generated_from_seed_indexed([0; 32], index).1
+    }
+
+    /// Time given to the solver to subscribe before the first event is served
+    const SUBSCRIBE_DELAY: Duration = Duration::from_secs(2);
+    /// How often paused event generation checks whether it was resumed
+    const PAUSE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+    /// Events served by the mock events service.
+    pub enum MockEvents {
+        /// `ViewFinished` events for consecutive views, generated in bursts at random intervals
+        Generated,
+        /// No events other than the ones pushed through the [`MockEventsHandle`]
+        Manual,
+        /// The events of a recording, served once each
+        Replay {
+            startup_info: StartupInfo<SeqTypes>,
+            events: Vec<RecordedEvent>,
+            pace: ReplayPace,
+        },
+    }
+
+    impl MockEvents {
+        /// Replays the recording at `path`, see [`crate::recording`].
+        pub fn replay_file(path: &Path, pace: ReplayPace) -> anyhow::Result<Self> {
+            let (startup_info, events) = read_recording(File::open(path)?)?;
+
+            Ok(Self::Replay {
+                startup_info,
+                events,
+                pace,
+            })
+        }
+    }
+
+    /// How fast a recording is replayed.
+    pub enum ReplayPace {
+        /// With the delays between events they were recorded with
+        Recorded,
+        /// With a fixed delay between events
+        Interval(Duration),
+        /// One event for every message received, starting right away. Replay stops when the
+        /// sender is dropped.
+        Step(Receiver<()>),
+    }
+
     pub fn generate_stake_table() -> Vec<PeerConfig<BLSPubKey>> {
-        (0..STAKED_NODES)
-            .map(|_| {
-                let private_key =
-                    <BLSPubKey as SignatureKey>::PrivateKey::generate(&mut rand::thread_rng());
-                let pub_key = BLSPubKey::from_private(&private_key);
+        (0..STAKED_NODES as u64)
+            .map(|index| {
+                let pub_key = BLSPubKey::from_private(&staked_node_key(index));
                 let state_key_pair = StateKeyPair::generate();
 
                 PeerConfig::<BLSPubKey> {
@@ -112,14 +284,19 @@ pub mod mock {
             .collect()
     }
 
-    pub async fn generate_view_finished_events(streamer: Arc<RwLock<EventsStreamer<SeqTypes>>>) {
-        sleep(Duration::from_secs(2)).await;
+    pub async fn generate_view_finished_events(
+        streamer: Arc<RwLock<EventsStreamer<SeqTypes>>>,
+        paused: Arc<AtomicBool>,
+    ) {
+        sleep(SUBSCRIBE_DELAY).await;
 
         let mut view_number = ViewNumber::new(1);
 
         let mut count = 1;
         loop {
             while count % 10 != 0 {
+                wait_while_paused(&paused).await;
+
                 tracing::info!("generating ViewFinished event");
 
                 streamer
@@ -145,19 +322,48 @@ pub mod mock {
         }
     }
 
-    pub fn run_mock_event_service() -> (Url, JoinHandle<()>, JoinHandle<()>) {
-        let port = pick_unused_port().expect("no free port");
-        let url = Url::parse(format!("http://localhost:{port}").as_str()).unwrap();
+    pub async fn replay_events(
+        streamer: Arc<RwLock<EventsStreamer<SeqTypes>>>,
+        paused: Arc<AtomicBool>,
+        events: Vec<RecordedEvent>,
+        pace: ReplayPace,
+    ) {
+        if !matches!(pace, ReplayPace::Step(_)) {
+            sleep(SUBSCRIBE_DELAY).await;
+        }
 
-        let known_nodes_with_stake = generate_stake_table();
+        let mut previous = Duration::ZERO;
+        for recorded in events {
+            match &pace {
+                ReplayPace::Recorded => {
+                    sleep(recorded.offset.saturating_sub(previous)).await;
+                    previous = recorded.offset;
+                }
+                ReplayPace::Interval(interval) => sleep(*interval).await,
+                ReplayPace::Step(steps) => {
+                    if steps.recv().await.is_err() {
+                        return;
+                    }
+                }
+            }
+            wait_while_paused(&paused).await;
 
-        let events_streamer = Arc::new(RwLock::new(EventsStreamer::<SeqTypes>::new(
-            known_nodes_with_stake.clone(),
-            NON_STAKED_NODE_COUNT,
-        )));
+            tracing::info!("replaying event {:?}", recorded.event.event);
 
-        let mut app =
-            App::<_, hotshot_events_service::events::Error>::with_state(events_streamer.clone());
+            streamer.write().await.handle_event(recorded.event).await;
+        }
+
+        tracing::info!("replay finished");
+    }
+
+    async fn wait_while_paused(paused: &AtomicBool) {
+        while paused.load(Ordering::Relaxed) {
+            sleep(PAUSE_POLL_INTERVAL).await;
+        }
+    }
+
+    fn serve(url: Url, streamer: Arc<RwLock<EventsStreamer<SeqTypes>>>) -> JoinHandle<()> {
+        let mut app = App::<_, hotshot_events_service::events::Error>::with_state(streamer);
 
         let hotshot_events_api =
             hotshot_events_service::events::define_api::<_, _, StaticVer01>(&Default::default())
@@ -166,40 +372,176 @@ pub mod mock {
         app.register_module("events_api", hotshot_events_api)
             .expect("Failed to register hotshot events API");
 
-        // cleanup with a function that takes in a a future
-        let events_api_handle = async_spawn({
-            let url = url.clone();
-            async move {
-                {
-                    let _ = app.serve(url, StaticVer01::instance()).await;
-                }
+        async_spawn(async move {
+            let _ = app.serve(url, StaticVer01::instance()).await;
+        })
+    }
+
+    /// Controls a running mock events service.
+    ///
+    /// Events pushed through the handle are published right away, independently of the events
+    /// the service generates or replays by itself.
+    pub struct MockEventsHandle {
+        url: Url,
+        streamer: Arc<RwLock<EventsStreamer<SeqTypes>>>,
+        paused: Arc<AtomicBool>,
+        startup_info: Mutex<(Vec<PeerConfig<BLSPubKey>>, usize)>,
+        server: Mutex<Option<JoinHandle<()>>>,
+        generator: Mutex<Option<JoinHandle<()>>>,
+    }
+
+    impl MockEventsHandle {
+        /// Publishes an event to every subscriber.
+        pub async fn push(&self, view_number: ViewNumber, event: EventType<SeqTypes>) {
+            self.streamer
+                .write()
+                .await
+                .handle_event(Event { view_number, event })
+                .await;
+        }
+
+        /// Publishes a `ViewFinished` event for `view_number`.
+        pub async fn finish_view(&self, view_number: ViewNumber) {
+            self.push(view_number + 1, EventType::ViewFinished { view_number })
+                .await;
+        }
+
+        /// Stops generating or replaying events until [`Self::resume`].
+        pub fn pause(&self) {
+            self.paused.store(true, Ordering::Relaxed);
+        }
+
+        pub fn resume(&self) {
+            self.paused.store(false, Ordering::Relaxed);
+        }
+
+        /// Simulates an outage: the event streams of all subscribers are closed and the events
+        /// API is unreachable until [`Self::reconnect`]. Events published in the meantime are
+        /// lost.
+        pub async fn disconnect(&self) {
+            if let Some(server) = self.server.lock().await.take() {
+                server.cancel().await;
             }
-        });
-        let generate_events_handle =
-            async_spawn(generate_view_finished_events(events_streamer.clone()));
+            self.reset_streamer().await;
+        }
+
+        /// Serves the events API again after [`Self::disconnect`].
+        pub async fn reconnect(&self) {
+            let mut server = self.server.lock().await;
+            if server.is_none() {
+                *server = Some(serve(self.url.clone(), self.streamer.clone()));
+            }
+        }
+
+        /// Replaces the stake table returned by `startup_info`.
+        ///
+        /// Like a restart of the events service, this closes the event streams of all
+        /// subscribers.
+        pub async fn set_stake_table(
+            &self,
+            known_nodes_with_stake: Vec<PeerConfig<BLSPubKey>>,
+            non_staked_node_count: usize,
+        ) {
+            *self.startup_info.lock().await = (known_nodes_with_stake, non_staked_node_count);
+            self.reset_streamer().await;
+        }
+
+        async fn reset_streamer(&self) {
+            let (known_nodes_with_stake, non_staked_node_count) =
+                self.startup_info.lock().await.clone();
+
+            // Dropping the old streamer drops the senders of all subscriptions
+            *self.streamer.write().await =
+                EventsStreamer::new(known_nodes_with_stake, non_staked_node_count);
+        }
+
+        /// Stops serving the events API and generating events.
+        pub async fn stop(&self) {
+            if let Some(generator) = self.generator.lock().await.take() {
+                generator.cancel().await;
+            }
+            if let Some(server) = self.server.lock().await.take() {
+                server.cancel().await;
+            }
+        }
+    }
+
+    pub fn run_mock_event_service() -> (Url, MockEventsHandle) {
+        run_mock_event_service_with(MockEvents::Generated)
+    }
+
+    pub fn run_mock_event_service_with(events: MockEvents) -> (Url, MockEventsHandle) {
+        let port = pick_unused_port().expect("no free port");
+        let url = Url::parse(format!("http://localhost:{port}").as_str()).unwrap();
+
+        let (known_nodes_with_stake, non_staked_node_count) = match &events {
+            MockEvents::Generated | MockEvents::Manual => {
+                (generate_stake_table(), NON_STAKED_NODE_COUNT)
+            }
+            MockEvents::Replay { startup_info, .. } => (
+                startup_info.known_node_with_stake.clone(),
+                startup_info.non_staked_node_count,
+            ),
+        };
+
+        let events_streamer = Arc::new(RwLock::new(EventsStreamer::<SeqTypes>::new(
+            known_nodes_with_stake.clone(),
+            non_staked_node_count,
+        )));
+        let paused = Arc::new(AtomicBool::new(false));
+
+        let server = serve(url.clone(), events_streamer.clone());
+        let generator = match events {
+            MockEvents::Generated => Some(async_spawn(generate_view_finished_events(
+                events_streamer.clone(),
+                paused.clone(),
+            ))),
+            MockEvents::Replay { events, pace, .. } => Some(async_spawn(replay_events(
+                events_streamer.clone(),
+                paused.clone(),
+                events,
+                pace,
+            ))),
+            MockEvents::Manual => None,
+        };
+
+        let handle = MockEventsHandle {
+            url: url.clone(),
+            streamer: events_streamer,
+            paused,
+            startup_info: Mutex::new((known_nodes_with_stake, non_staked_node_count)),
+            server: Mutex::new(Some(server)),
+            generator: Mutex::new(generator),
+        };
 
         let url = url.join("events_api").unwrap();
 
-        (url, events_api_handle, generate_events_handle)
+        (url, handle)
     }
 }
 
 #[cfg(test)]
 mod test {
 
+    use std::time::Duration;
+
     use async_compatibility_layer::logging::setup_logging;
-    use async_std::stream::StreamExt;
+    use async_std::{future::timeout, stream::StreamExt, task::sleep};
     use espresso_types::SeqTypes;
-    use hotshot::types::Event;
+    use hotshot::types::{Event, EventType};
     use hotshot_events_service::events_source::StartupInfo;
+    use hotshot_types::{data::ViewNumber, traits::node_implementation::ConsensusTime};
     use surf_disco::Client;
 
-    use crate::mock::{run_mock_event_service, StaticVer01};
+    use crate::mock::{
+        generate_stake_table, run_mock_event_service, run_mock_event_service_with, MockEvents,
+        StaticVer01,
+    };
 
     #[async_std::test]
     async fn test_mock_events_service() {
         setup_logging();
-        let (url, handle1, handle2) = run_mock_event_service();
+        let (url, handle) = run_mock_event_service();
 
         tracing::info!("running event service");
 
@@ -232,7 +574,77 @@ mod test {
             }
         }
 
-        handle1.cancel().await;
-        handle2.cancel().await;
+        handle.stop().await;
+    }
+
+    #[async_std::test]
+    async fn test_controlled_mock_events_service() {
+        setup_logging();
+        let (url, handle) = run_mock_event_service_with(MockEvents::Manual);
+
+        let client = Client::<hotshot_events_service::events::Error, StaticVer01>::new(url);
+        client.connect(None).await;
+
+        let subscribe = || async {
+            let events = client
+                .socket("events")
+                .subscribe::<Event<SeqTypes>>()
+                .await
+                .unwrap();
+            // Give the service time to register the subscription
+            sleep(Duration::from_millis(500)).await;
+            events
+        };
+        let mut events = subscribe().await;
+
+        // Only the pushed events are served, in order
+        handle.finish_view(ViewNumber::new(5)).await;
+        handle
+            .push(
+                ViewNumber::new(7),
+                EventType::ViewTimeout {
+                    view_number: ViewNumber::new(6),
+                },
+            )
+            .await;
+
+        let event = events.next().await.unwrap().unwrap();
+        assert_eq!(event.view_number, ViewNumber::new(6));
+        assert!(matches!(
+            event.event,
+            EventType::ViewFinished { view_number } if view_number == ViewNumber::new(5)
+        ));
+        let event = events.next().await.unwrap().unwrap();
+        assert!(matches!(
+            event.event,
+            EventType::ViewTimeout { view_number } if view_number == ViewNumber::new(6)
+        ));
+
+        // Changing the stake table closes the event stream
+        let stake_table = generate_stake_table();
+        handle.set_stake_table(stake_table[..3].to_vec(), 0).await;
+
+        let next = timeout(Duration::from_secs(5), events.next())
+            .await
+            .expect("event stream was not closed");
+        assert!(!matches!(next, Some(Ok(_))));
+
+        let startup_info: StartupInfo<SeqTypes> = client.get("startup_info").send().await.unwrap();
+        assert_eq!(startup_info.known_node_with_stake.len(), 3);
+        assert_eq!(startup_info.non_staked_node_count, 0);
+
+        // During an outage the service is unreachable
+        handle.disconnect().await;
+        assert!(!client.connect(Some(Duration::from_secs(1))).await);
+
+        handle.reconnect().await;
+        assert!(client.connect(Some(Duration::from_secs(5))).await);
+
+        let mut events = subscribe().await;
+        handle.finish_view(ViewNumber::new(8)).await;
+        let event = events.next().await.unwrap().unwrap();
+        assert_eq!(event.view_number, ViewNumber::new(9));
+
+        handle.stop().await;
     }
 }
@@ -0,0 +1,110 @@
+use std::str::FromStr;
+
+use async_broadcast::{broadcast, InactiveReceiver, Sender};
+use espresso_types::{v0_3::RollupRegistration, NamespaceId};
+use futures::stream::{BoxStream, StreamExt};
+use serde::{Deserialize, Serialize};
+
+use crate::{signing::SignedAuctionResults, SolverError};
+
+/// How many messages a slow subscriber may fall behind before it misses the oldest ones
+const FEED_CAPACITY: usize = 1024;
+
+/// Broadcasts messages to every subscriber that is connected at the time of publishing.
+///
+/// Publishing never waits for subscribers: once a subscriber is `FEED_CAPACITY` messages
+/// behind, the oldest message it has not received yet is dropped.
+#[derive(Debug)]
+pub struct Feed<T> {
+    sender: Sender<T>,
+    // Keeps the channel open while nobody is subscribed
+    receiver: InactiveReceiver<T>,
+}
+
+impl<T: Clone + Send + 'static> Feed<T> {
+    pub fn new() -> Self {
+        let (mut sender, receiver) = broadcast(FEED_CAPACITY);
+        sender.set_overflow(true);
+
+        Self {
+            sender,
+            receiver: receiver.deactivate(),
+        }
+    }
+
+    pub fn publish(&self, msg: T) {
+        // Fails only if there are no subscribers, in which case there is nobody to tell
+        let _ = self.sender.try_broadcast(msg);
+    }
+
+    pub fn subscribe(&self) -> BoxStream<'static, T> {
+        self.receiver.activate_cloned().boxed()
+    }
+}
+
+impl<T: Clone + Send + 'static> Default for Feed<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Published on the `auction_results` stream whenever the auction for a view is finalized.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FinalizedAuction {
+    pub view_number: u64,
+    pub results: SignedAuctionResults,
+    /// Number of bids the auction was computed from, if the subscriber asked for it
+    pub bid_count: Option<usize>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RegistrationChangeKind {
+    Registered,
+    Updated,
+    /// An update that set an active registration inactive
+    Deactivated,
+    /// Removed by an operator
+    Deleted,
+}
+
+impl RegistrationChangeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Registered => "registered",
+            Self::Updated => "updated",
+            Self::Deactivated => "deactivated",
+            Self::Deleted => "deleted",
+        }
+    }
+}
+
+impl FromStr for RegistrationChangeKind {
+    type Err = SolverError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "registered" => Ok(Self::Registered),
+            "updated" => Ok(Self::Updated),
+            "deactivated" => Ok(Self::Deactivated),
+            "deleted" => Ok(Self::Deleted),
+            _ => Err(SolverError::Database(format!(
+                "unknown registration change kind {s}"
+            ))),
+        }
+    }
+}
+
+/// A change to a rollup registration, as published on the `registration_changes` stream.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegistrationChange {
+    /// Position of the change in the feed. Subscribing with this cursor resumes after the change.
+    pub cursor: u64,
+    pub namespace_id: NamespaceId,
+    pub kind: RegistrationChangeKind,
+    /// The registration after the change, `None` if it was deleted
+    pub registration: Option<RollupRegistration>,
+    /// Latest view seen from the events service when the change took effect
+    pub view_number: Option<u64>,
+    /// Unix time the change took effect at, in seconds
+    pub timestamp: u64,
+}
@@ -1,12 +1,30 @@
+pub mod admin;
 mod api;
+pub mod auction;
+pub mod bid_book;
+pub mod builders;
+pub mod client;
 pub mod database;
 mod events;
+pub mod feeds;
+mod limits;
+pub mod listener;
+pub mod metrics;
 mod options;
+pub mod recording;
+mod registration_model;
+pub mod shutdown;
+pub mod signing;
+pub mod simulation;
 pub mod state;
-mod testing;
+mod status;
+pub mod storage;
+pub mod testing;
+pub mod workload;
 
 pub use api::*;
 pub use events::*;
 pub use options::*;
+pub use status::*;
 
 type SolverResult<T> = Result<T, SolverError>;
@@ -0,0 +1,179 @@
+use std::{hash::Hash, net::SocketAddr, num::NonZeroUsize, sync::Mutex, time::Instant};
+
+use lru::LruCache;
+use tide_disco::RequestParams;
+
+use crate::{RateLimitOptions, SolverError, SolverResult};
+
+/// A limiter tracks at most this many keys, evicting the least recently used one
+const MAX_TRACKED_KEYS: usize = 10_000;
+
+#[derive(Clone, Copy, Debug)]
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Token bucket rate limiter keyed by `K`.
+///
+/// Each key may make `burst` requests at once and `rate` requests per second after that.
+#[derive(Debug)]
+pub(crate) struct RateLimiter<K> {
+    rate: f64,
+    burst: f64,
+    buckets: Mutex<LruCache<K, TokenBucket>>,
+}
+
+impl<K: Eq + Hash> RateLimiter<K> {
+    pub(crate) fn new(rate: f64, burst: u32) -> Self {
+        Self {
+            rate,
+            burst: burst.into(),
+            buckets: Mutex::new(LruCache::new(NonZeroUsize::new(MAX_TRACKED_KEYS).unwrap())),
+        }
+    }
+
+    /// Takes a token from the bucket of `key`, returning `false` if it is empty.
+    pub(crate) fn try_acquire(&self, key: K) -> bool {
+        self.try_acquire_at(key, Instant::now())
+    }
+
+    fn try_acquire_at(&self, key: K, now: Instant) -> bool {
+        let mut buckets = self.buckets.lock().unwrap();
+
+        // An evicted bucket starts over full, which can only happen to a key that made no request
+        // while `MAX_TRACKED_KEYS` other keys did
+        let bucket = buckets.get_or_insert_mut(key, || TokenBucket {
+            tokens: self.burst,
+            updated: now,
+        });
+
+        if refill(bucket, self.rate, self.burst, now) < 1.0 {
+            return false;
+        }
+
+        bucket.tokens -= 1.0;
+        true
+    }
+}
+
+fn refill(bucket: &mut TokenBucket, rate: f64, burst: f64, now: Instant) -> f64 {
+    let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+    bucket.tokens = (bucket.tokens + elapsed * rate).min(burst);
+    bucket.updated = now;
+    bucket.tokens
+}
+
+/// Limits applied to the unauthenticated POST routes of the solver API.
+#[derive(Debug)]
+pub(crate) struct RequestLimits {
+    per_key: RateLimiter<String>,
+    per_ip: RateLimiter<String>,
+    max_body_size: usize,
+}
+
+impl RequestLimits {
+    pub(crate) fn new(options: &RateLimitOptions) -> Self {
+        Self {
+            per_key: RateLimiter::new(options.per_key_rate, options.per_key_burst),
+            per_ip: RateLimiter::new(options.per_ip_rate, options.per_ip_burst),
+            max_body_size: options.max_body_size,
+        }
+    }
+
+    /// Checks the size of the request body and the rate limit of the client address.
+    ///
+    /// The listener stops reading bodies past the limit, so an oversized body is at most one byte
+    /// over it here.
+    pub(crate) fn check_request(&self, req: &RequestParams) -> SolverResult<()> {
+        let size = req.body_bytes().len();
+        if size > self.max_body_size {
+            return Err(SolverError::RequestTooLarge {
+                size,
+                max: self.max_body_size,
+            });
+        }
+
+        // Requests without a known address share a single bucket
+        let ip = req
+            .remote()
+            .map(|remote| {
+                remote
+                    .parse::<SocketAddr>()
+                    .map_or(remote.to_string(), |addr| addr.ip().to_string())
+            })
+            .unwrap_or_default();
+
+        if !self.per_ip.try_acquire(ip.clone()) {
+            return Err(SolverError::RateLimited(format!("address {ip}")));
+        }
+
+        Ok(())
+    }
+
+    /// Checks the rate limit of the builder or rollup key that signed the request.
+    ///
+    /// Only call this once the signature is verified, otherwise anyone could drain the bucket of
+    /// someone else's key.
+    pub(crate) fn check_key(&self, key: impl ToString) -> SolverResult<()> {
+        let key = key.to_string();
+
+        if !self.per_key.try_acquire(key.clone()) {
+            return Err(SolverError::RateLimited(format!("key {key}")));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_token_bucket() {
+        let limiter = RateLimiter::new(2.0, 3);
+        let start = Instant::now();
+
+        // The burst is available immediately, then the bucket is empty
+        for _ in 0..3 {
+            assert!(limiter.try_acquire_at("a", start));
+        }
+        assert!(!limiter.try_acquire_at("a", start));
+
+        // Other keys have their own bucket
+        assert!(limiter.try_acquire_at("b", start));
+
+        // Tokens refill at the configured rate, up to the burst
+        assert!(limiter.try_acquire_at("a", start + Duration::from_millis(500)));
+        assert!(!limiter.try_acquire_at("a", start + Duration::from_millis(500)));
+
+        let later = start + Duration::from_secs(60);
+        for _ in 0..3 {
+            assert!(limiter.try_acquire_at("a", later));
+        }
+        assert!(!limiter.try_acquire_at("a", later));
+    }
+
+    #[test]
+    fn test_eviction() {
+        let limiter = RateLimiter::new(0.0, 1);
+        let now = Instant::now();
+
+        assert!(limiter.try_acquire_at(0, now));
+        assert!(!limiter.try_acquire_at(0, now));
+
+        // A drained bucket stays drained while fewer than the maximum of other keys are used
+        for key in 1..MAX_TRACKED_KEYS {
+            assert!(limiter.try_acquire_at(key, now));
+        }
+        assert!(!limiter.try_acquire_at(0, now));
+
+        // Only the least recently used bucket is evicted
+        assert!(limiter.try_acquire_at(MAX_TRACKED_KEYS, now));
+        assert!(!limiter.try_acquire_at(0, now));
+        assert!(limiter.try_acquire_at(1, now));
+    }
+}
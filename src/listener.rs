@@ -0,0 +1,283 @@
+//! TCP listener of the solver's HTTP server.
+//!
+//! tide-disco reads the whole body of a request before a handler sees it, so a size limit
+//! checked by the handler only applies after the memory is spent. The listener rejects requests
+//! whose declared length exceeds the limit without reading them, and stops reading a body without
+//! a declared length once it passes the limit, leaving the handler at most one byte over it to
+//! detect.
+//!
+//! The listener can also serve a route under a second path, such as the metrics of the status API
+//! at `/metrics`, where Prometheus looks for them by default.
+//!
+//! On shutdown, a [`ListenerHandle`] stops the listener from accepting connections and then waits
+//! for the requests in flight to finish.
+use std::{
+    fmt::{self, Debug, Display},
+    io,
+    pin::pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use async_std::{
+    channel::{self, Receiver, Sender},
+    future::timeout,
+    io::{BufReader, ReadExt},
+    net::{TcpListener, TcpStream},
+    stream::StreamExt,
+    task,
+};
+use async_trait::async_trait;
+use futures::future::{select, Either};
+use tide::{
+    http::{Body, Request, Response, StatusCode},
+    listener::{ListenInfo, Listener},
+    Server,
+};
+
+use crate::SolverError;
+
+/// How often draining checks whether the requests in flight have finished
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+pub struct SolverListener<State> {
+    addr: String,
+    max_body_size: usize,
+    // Exact request paths rewritten to the path of the route serving them
+    aliases: Arc<Vec<(String, String)>>,
+    listener: Option<TcpListener>,
+    server: Option<Server<State>>,
+    info: Option<ListenInfo>,
+    handle: ListenerHandle,
+    // Closed by `ListenerHandle::stop`
+    stopped: Receiver<()>,
+}
+
+impl<State> SolverListener<State> {
+    /// Listens on `host:port`, accepting request bodies of at most `max_body_size` bytes.
+    pub fn new(host: &str, port: u16, max_body_size: usize) -> Self {
+        let (stop, stopped) = channel::bounded(1);
+
+        Self {
+            addr: format!("{host}:{port}"),
+            max_body_size,
+            aliases: Default::default(),
+            listener: None,
+            server: None,
+            info: None,
+            handle: ListenerHandle {
+                stop,
+                in_flight: Default::default(),
+            },
+            stopped,
+        }
+    }
+
+    /// Serves requests for the path `from` with the route at the path `to`.
+    pub fn with_alias(mut self, from: &str, to: &str) -> Self {
+        Arc::make_mut(&mut self.aliases).push((from.to_string(), to.to_string()));
+        self
+    }
+
+    /// A handle to stop the listener and drain its requests once it is serving.
+    pub fn handle(&self) -> ListenerHandle {
+        self.handle.clone()
+    }
+}
+
+/// Controls a [`SolverListener`] from outside the server.
+#[derive(Clone, Debug)]
+pub struct ListenerHandle {
+    stop: Sender<()>,
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl ListenerHandle {
+    /// Stops accepting connections, which ends the server. Requests on connections accepted
+    /// before are still served.
+    pub fn stop(&self) {
+        self.stop.close();
+    }
+
+    /// Number of requests being handled.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Waits until no request is in flight, for at most `deadline`. Returns whether all requests
+    /// finished.
+    pub async fn drain(&self, deadline: Duration) -> bool {
+        timeout(deadline, async {
+            while self.in_flight() > 0 {
+                task::sleep(DRAIN_POLL_INTERVAL).await;
+            }
+        })
+        .await
+        .is_ok()
+    }
+}
+
+/// Counts a request as in flight until dropped, including when its handler panics.
+struct InFlight(Arc<AtomicUsize>);
+
+impl InFlight {
+    fn new(in_flight: &Arc<AtomicUsize>) -> Self {
+        in_flight.fetch_add(1, Ordering::SeqCst);
+        Self(in_flight.clone())
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[async_trait]
+impl<State> Listener<State> for SolverListener<State>
+where
+    State: Clone + Send + Sync + 'static,
+{
+    async fn bind(&mut self, server: Server<State>) -> io::Result<()> {
+        assert!(self.server.is_none(), "`bind` must only be called once");
+
+        let listener = TcpListener::bind(&self.addr).await?;
+        self.info = Some(ListenInfo::new(
+            format!("http://{}", listener.local_addr()?),
+            "tcp".to_string(),
+            false,
+        ));
+        self.listener = Some(listener);
+        self.server = Some(server);
+
+        Ok(())
+    }
+
+    async fn accept(&mut self) -> io::Result<()> {
+        let server = self
+            .server
+            .take()
+            .expect("`bind` must be called before `accept`");
+        let listener = self
+            .listener
+            .take()
+            .expect("`bind` must be called before `accept`");
+
+        let mut incoming = listener.incoming();
+        loop {
+            let stream = match select(pin!(incoming.next()), pin!(self.stopped.recv())).await {
+                Either::Left((Some(stream), _)) => stream,
+                // Either the listener failed for good or the handle stopped it
+                Either::Left((None, _)) | Either::Right(_) => break,
+            };
+
+            match stream {
+                Ok(stream) => handle_connection(
+                    server.clone(),
+                    stream,
+                    self.max_body_size,
+                    self.aliases.clone(),
+                    self.handle.in_flight.clone(),
+                ),
+                Err(err) if is_transient(&err) => continue,
+                Err(err) => {
+                    tracing::error!("failed to accept connection: {err}");
+                    task::sleep(Duration::from_millis(500)).await;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn info(&self) -> Vec<ListenInfo> {
+        self.info.iter().cloned().collect()
+    }
+}
+
+impl<State> Debug for SolverListener<State> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SolverListener")
+            .field("addr", &self.addr)
+            .field("max_body_size", &self.max_body_size)
+            .field("aliases", &self.aliases)
+            .finish()
+    }
+}
+
+impl<State> Display for SolverListener<State> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "http://{}", self.addr)
+    }
+}
+
+fn handle_connection<State>(
+    server: Server<State>,
+    stream: TcpStream,
+    max_body_size: usize,
+    aliases: Arc<Vec<(String, String)>>,
+    in_flight: Arc<AtomicUsize>,
+) where
+    State: Clone + Send + Sync + 'static,
+{
+    task::spawn(async move {
+        let local_addr = stream.local_addr().ok();
+        let peer_addr = stream.peer_addr().ok();
+
+        let result = async_h1::accept(stream, |mut req| async {
+            let _in_flight = InFlight::new(&in_flight);
+            req.set_local_addr(local_addr);
+            req.set_peer_addr(peer_addr);
+            respond(&server, req, max_body_size, &aliases).await
+        })
+        .await;
+
+        if let Err(err) = result {
+            tracing::debug!("connection from {peer_addr:?} failed: {err}");
+        }
+    });
+}
+
+async fn respond<State>(
+    server: &Server<State>,
+    mut req: Request,
+    max_body_size: usize,
+    aliases: &[(String, String)],
+) -> tide::http::Result<Response>
+where
+    State: Clone + Send + Sync + 'static,
+{
+    if let Some(size) = req.len().filter(|size| *size > max_body_size) {
+        let mut res = Response::new(StatusCode::PayloadTooLarge);
+        res.set_body(Body::from_json(&SolverError::RequestTooLarge {
+            size,
+            max: max_body_size,
+        })?);
+        return Ok(res);
+    }
+
+    // Keep the content type, which `set_body` would otherwise take from the new body
+    let body = req.take_body();
+    let mime = body.mime().clone();
+    let mut limited = Body::from_reader(BufReader::new(body.take(max_body_size as u64 + 1)), None);
+    limited.set_mime(mime);
+    req.set_body(limited);
+
+    if let Some((_, to)) = aliases.iter().find(|(from, _)| req.url().path() == from) {
+        req.url_mut().set_path(to);
+    }
+
+    server.respond(req).await
+}
+
+fn is_transient(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionReset
+    )
+}
@@ -1,10 +1,13 @@
-use std::sync::Arc;
+use std::{pin::pin, sync::Arc, time::Instant};
 
 use async_compatibility_layer::art::async_spawn;
-use async_std::sync::RwLock;
 use clap::Parser;
+use futures::future::{select, Either};
 use marketplace_solver::{
-    define_api, handle_events,
+    admin::define_admin_api,
+    define_api, define_status_api, handle_events_with_reconnect,
+    listener::SolverListener,
+    shutdown::{shutdown, wait_for_signal},
     state::{GlobalState, SolverState, StakeTable},
     EventsServiceClient, Options, SolverError,
 };
@@ -23,37 +26,107 @@ pub const SOLVER_VERSION: SolverVersion = StaticVersion {};
 pub async fn main() {
     let options = Options::parse();
     let database_options = options.database_options;
+    let server_options = options.server_options;
 
     let events_api_url = options.events_url;
 
-    let client = EventsServiceClient::new(events_api_url).await;
+    let client = EventsServiceClient::new(events_api_url.clone()).await;
     let startup_info = client.get_startup_info().await.unwrap();
-    let stream = client.get_event_stream().await.unwrap();
 
-    let solver_state = SolverState {
-        stake_table: StakeTable {
-            known_nodes_with_stake: startup_info.known_node_with_stake,
-        },
-        bid_txs: Default::default(),
-    };
+    let solver_state = SolverState::new(StakeTable {
+        known_nodes_with_stake: startup_info.known_node_with_stake,
+    });
 
     let db = database_options
         .connect()
         .await
         .expect("failed to create database");
-    let state = Arc::new(RwLock::new(GlobalState::new(db, solver_state).unwrap()));
+    let solver_key = options
+        .solver_key_options
+        .load()
+        .expect("failed to load solver key");
 
-    let _handle = async_spawn(handle_events(stream, state.clone()));
+    // The state synchronizes concurrent updates itself, so it is shared without a lock
+    let state = Arc::new(
+        GlobalState::new(
+            Arc::new(db),
+            solver_state,
+            options.auction_options,
+            solver_key,
+        )
+        .unwrap(),
+    );
 
-    let mut app = App::<_, SolverError>::with_state(state);
+    let event_handler = async_spawn(handle_events_with_reconnect(events_api_url, state.clone()));
+
+    let mut app = App::<_, SolverError>::with_state(state.clone());
     app.with_version(env!("CARGO_PKG_VERSION").parse().unwrap());
 
-    let mut api = define_api(Default::default()).unwrap();
+    let mut api_options = options
+        .api_options
+        .load()
+        .expect("failed to load API specification");
+    let max_body_size = options.rate_limit_options.max_body_size;
+    api_options.limits = options.rate_limit_options;
+    let mut api = define_api(api_options).unwrap();
     api.with_version(env!("CARGO_PKG_VERSION").parse().unwrap());
 
-    app.register_module::<SolverError, SolverVersion>("hello", api)
+    app.register_module::<SolverError, SolverVersion>(&server_options.api_prefix, api)
+        .unwrap();
+
+    let mut status_api = define_status_api().unwrap();
+    status_api.with_version(env!("CARGO_PKG_VERSION").parse().unwrap());
+
+    app.register_module::<SolverError, SolverVersion>("status", status_api)
+        .unwrap();
+
+    let mut admin_api = define_admin_api(options.admin_options).unwrap();
+    admin_api.with_version(env!("CARGO_PKG_VERSION").parse().unwrap());
+
+    app.register_module::<SolverError, SolverVersion>("admin", admin_api)
         .unwrap();
-    let _ = app
-        .serve(format!("http://0.0.0.0:{}", 7777), StaticVer01::instance())
-        .await;
+    let bind_url = server_options.bind_url().expect("invalid listen address");
+    tracing::info!(
+        "serving solver API at {}",
+        server_options.solver_api_url(&bind_url).unwrap()
+    );
+
+    let listener = SolverListener::new(&server_options.host, server_options.port, max_body_size)
+        .with_alias("/metrics", "/status/metrics");
+    let listener_handle = listener.handle();
+    let server = pin!(app.serve(listener, StaticVer01::instance()));
+    let signal = pin!(wait_for_signal());
+
+    match select(server, signal).await {
+        Either::Left((result, _)) => {
+            if let Err(err) = result {
+                tracing::error!("solver API server stopped: {err}");
+            }
+        }
+        Either::Right((result, server)) => {
+            result.expect("failed to wait for shutdown signal");
+
+            // Stop accepting connections, but let the requests in flight finish
+            listener_handle.stop();
+            if let Err(err) = server.await {
+                tracing::error!("solver API server stopped: {err}");
+            }
+        }
+    }
+
+    tracing::info!("shutting down");
+
+    let start = Instant::now();
+    let deadline = server_options.shutdown_timeout;
+    if !listener_handle.drain(deadline).await {
+        tracing::warn!(
+            "{} requests still in flight after {deadline:?}",
+            listener_handle.in_flight()
+        );
+    }
+
+    let remaining = deadline.saturating_sub(start.elapsed());
+    if let Err(err) = shutdown(state, event_handler, remaining).await {
+        tracing::error!("{err:#}");
+    }
 }
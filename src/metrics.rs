@@ -0,0 +1,152 @@
+use prometheus::{Histogram, HistogramOpts, IntCounter, IntCounterVec, IntGauge, Opts, Registry};
+
+use crate::SolverError;
+
+/// Metrics exported by the solver on the `metrics` route of the status API.
+///
+/// Prometheus metrics are reference counted internally, so clones update the same values.
+#[derive(Clone)]
+pub struct SolverMetrics {
+    registry: Registry,
+    pub bids_received: IntCounter,
+    pub bids_accepted: IntCounter,
+    pub bids_rejected: IntCounterVec,
+    pub bids_skipped: IntCounter,
+    pub registered_rollups: IntGauge,
+    pub active_rollups: IntGauge,
+    pub auction_computation_seconds: Histogram,
+    pub last_view_finished: IntGauge,
+    pub event_stream_reconnects: IntCounter,
+    pub database_connections: IntGauge,
+    pub database_idle_connections: IntGauge,
+}
+
+impl SolverMetrics {
+    pub fn new() -> Self {
+        let registry = Registry::new_custom(Some("marketplace_solver".to_string()), None)
+            .expect("valid metrics prefix");
+
+        let bids_received =
+            IntCounter::new("bids_received", "Bids submitted to the solver").unwrap();
+        let bids_accepted =
+            IntCounter::new("bids_accepted", "Bids that passed validation").unwrap();
+        let bids_rejected = IntCounterVec::new(
+            Opts::new("bids_rejected", "Bids that were rejected, by reason"),
+            &["reason"],
+        )
+        .unwrap();
+        let bids_skipped = IntCounter::new(
+            "bids_skipped",
+            "Accepted bids dropped because their view finished without an auction",
+        )
+        .unwrap();
+        let registered_rollups =
+            IntGauge::new("registered_rollups", "Number of registered rollups").unwrap();
+        let active_rollups =
+            IntGauge::new("active_rollups", "Number of active rollup registrations").unwrap();
+        let auction_computation_seconds = Histogram::with_opts(HistogramOpts::new(
+            "auction_computation_seconds",
+            "Time spent computing the results of an auction",
+        ))
+        .unwrap();
+        let last_view_finished = IntGauge::new(
+            "last_view_finished",
+            "Last view of a `ViewFinished` event received from the events service",
+        )
+        .unwrap();
+        let event_stream_reconnects = IntCounter::new(
+            "event_stream_reconnects",
+            "Number of times the solver reconnected to the events service",
+        )
+        .unwrap();
+        let database_connections = IntGauge::new(
+            "database_connections",
+            "Connections currently held by the Postgres pool",
+        )
+        .unwrap();
+        let database_idle_connections = IntGauge::new(
+            "database_idle_connections",
+            "Idle connections in the Postgres pool",
+        )
+        .unwrap();
+
+        registry.register(Box::new(bids_received.clone())).unwrap();
+        registry.register(Box::new(bids_accepted.clone())).unwrap();
+        registry.register(Box::new(bids_rejected.clone())).unwrap();
+        registry.register(Box::new(bids_skipped.clone())).unwrap();
+        registry
+            .register(Box::new(registered_rollups.clone()))
+            .unwrap();
+        registry.register(Box::new(active_rollups.clone())).unwrap();
+        registry
+            .register(Box::new(auction_computation_seconds.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(last_view_finished.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(event_stream_reconnects.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(database_connections.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(database_idle_connections.clone()))
+            .unwrap();
+
+        Self {
+            registry,
+            bids_received,
+            bids_accepted,
+            bids_rejected,
+            bids_skipped,
+            registered_rollups,
+            active_rollups,
+            auction_computation_seconds,
+            last_view_finished,
+            event_stream_reconnects,
+            database_connections,
+            database_idle_connections,
+        }
+    }
+
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    pub fn bid_rejected(&self, err: &SolverError) {
+        self.bids_rejected
+            .with_label_values(&[rejection_reason(err)])
+            .inc();
+    }
+}
+
+impl Default for SolverMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Label for the `bids_rejected` metric, also used to group rejections in load test reports.
+pub fn rejection_reason(err: &SolverError) -> &'static str {
+    match err {
+        SolverError::InvalidBidSignature(_) => "invalid_signature",
+        SolverError::EmptyBidNamespaces => "empty_namespaces",
+        SolverError::DuplicateBidNamespace(_) => "duplicate_namespace",
+        SolverError::UnregisteredNamespace(_) => "unregistered_namespace",
+        SolverError::InactiveNamespace(_) => "inactive_namespace",
+        SolverError::BidViewOutOfRange { .. } => "view_out_of_range",
+        SolverError::ZeroBidAmount => "zero_amount",
+        SolverError::BidAmountTooLarge { .. } => "amount_too_large",
+        SolverError::InvalidBuilderUrl(_) => "invalid_builder_url",
+        SolverError::ShuttingDown => "shutting_down",
+        SolverError::BiddingPaused => "bidding_paused",
+        SolverError::TooManyBids { .. } => "too_many_bids",
+        SolverError::UnregisteredBuilder(_) => "unregistered_builder",
+        SolverError::BuilderNotAllowed(_) => "builder_not_allowed",
+        SolverError::Database(_) => "database",
+        SolverError::RateLimited(_) => "rate_limited",
+        SolverError::RequestTooLarge { .. } => "request_too_large",
+        _ => "other",
+    }
+}
@@ -1,10 +1,12 @@
-use std::{str::FromStr, time::Duration};
+use std::{fmt, path::PathBuf, str::FromStr, time::Duration};
 
 use clap::Parser;
+use espresso_types::{FeeAccount, FeeAmount};
+use hotshot_types::signature_key::{BLSPrivKey, BLSPubKey};
 use thiserror::Error;
-use tide_disco::Url;
+use tide_disco::{api::ApiError, Url};
 
-use crate::database::PostgresClient;
+use crate::{database::PostgresClient, ApiOptions};
 
 // todo (abdul) remove
 #[derive(Parser, Clone, Debug)]
@@ -12,8 +14,175 @@ pub struct Options {
     #[clap(long, env = "HOTSHOT_EVENTS_API_URL")]
     pub events_url: Url,
 
+    #[clap(flatten)]
+    pub server_options: ServerOptions,
+
+    #[clap(flatten)]
+    pub api_options: ApiSpecOptions,
+
+    #[clap(flatten)]
+    pub rate_limit_options: RateLimitOptions,
+
+    #[clap(flatten)]
+    pub admin_options: AdminOptions,
+
     #[clap(flatten)]
     pub database_options: DatabaseOptions,
+
+    #[clap(flatten)]
+    pub auction_options: AuctionOptions,
+
+    #[clap(flatten)]
+    pub solver_key_options: SolverKeyOptions,
+}
+
+/// Arguments for serving the solver API
+#[derive(Clone, Debug, Parser)]
+pub struct ServerOptions {
+    /// Address the HTTP server listens on
+    #[clap(
+        long = "listen-host",
+        env = "MARKETPLACE_SOLVER_LISTEN_HOST",
+        default_value = "0.0.0.0"
+    )]
+    pub host: String,
+
+    #[clap(
+        long = "listen-port",
+        env = "MARKETPLACE_SOLVER_LISTEN_PORT",
+        default_value_t = 7777
+    )]
+    pub port: u16,
+
+    /// Module name the solver API is served under, e.g. `http://host:port/solver_api`
+    #[clap(
+        long,
+        env = "MARKETPLACE_SOLVER_API_PREFIX",
+        default_value = "solver_api"
+    )]
+    pub api_prefix: String,
+
+    /// How long in-flight work may take to finish after SIGTERM or SIGINT
+    #[clap(
+        long,
+        value_parser = parse_duration,
+        env = "MARKETPLACE_SOLVER_SHUTDOWN_TIMEOUT",
+        default_value = "10s"
+    )]
+    pub shutdown_timeout: Duration,
+}
+
+impl ServerOptions {
+    /// URL the HTTP server binds to.
+    pub fn bind_url(&self) -> anyhow::Result<Url> {
+        Ok(format!("http://{}:{}", self.host, self.port).parse()?)
+    }
+
+    /// Base URL of the solver API for a server reachable at `base`.
+    pub fn solver_api_url(&self, base: &Url) -> anyhow::Result<Url> {
+        Ok(base.join(&self.api_prefix)?)
+    }
+}
+
+impl Default for ServerOptions {
+    fn default() -> Self {
+        Self {
+            host: "0.0.0.0".to_string(),
+            port: 7777,
+            api_prefix: "solver_api".to_string(),
+            shutdown_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Arguments for customizing the solver API specification
+#[derive(Clone, Debug, Parser)]
+pub struct ApiSpecOptions {
+    /// TOML file replacing the built-in `solver.toml`
+    #[clap(long = "solver-api-path", env = "MARKETPLACE_SOLVER_API_PATH")]
+    pub api_path: Option<PathBuf>,
+
+    /// TOML files merged into the API specification, e.g. to add routes or override DOC strings
+    #[clap(
+        long = "solver-api-extension",
+        env = "MARKETPLACE_SOLVER_API_EXTENSIONS",
+        value_delimiter = ','
+    )]
+    pub extensions: Vec<PathBuf>,
+}
+
+impl ApiSpecOptions {
+    pub fn load(self) -> Result<ApiOptions, ApiError> {
+        ApiOptions::from_files(self.api_path, self.extensions)
+    }
+}
+
+/// Arguments for throttling the routes that accept submissions
+#[derive(Clone, Debug, Parser)]
+pub struct RateLimitOptions {
+    /// Requests per second a single builder or rollup key may sustain
+    #[clap(
+        long = "rate-limit-per-key",
+        env = "MARKETPLACE_SOLVER_RATE_LIMIT_PER_KEY",
+        default_value_t = 10.0
+    )]
+    pub per_key_rate: f64,
+
+    /// Requests a single builder or rollup key may make in a burst
+    #[clap(
+        long = "rate-limit-per-key-burst",
+        env = "MARKETPLACE_SOLVER_RATE_LIMIT_PER_KEY_BURST",
+        default_value_t = 20
+    )]
+    pub per_key_burst: u32,
+
+    /// Requests per second a single client address may sustain
+    #[clap(
+        long = "rate-limit-per-ip",
+        env = "MARKETPLACE_SOLVER_RATE_LIMIT_PER_IP",
+        default_value_t = 50.0
+    )]
+    pub per_ip_rate: f64,
+
+    /// Requests a single client address may make in a burst
+    #[clap(
+        long = "rate-limit-per-ip-burst",
+        env = "MARKETPLACE_SOLVER_RATE_LIMIT_PER_IP_BURST",
+        default_value_t = 100
+    )]
+    pub per_ip_burst: u32,
+
+    /// Maximum size of a request body, in bytes
+    #[clap(
+        long,
+        env = "MARKETPLACE_SOLVER_MAX_BODY_SIZE",
+        default_value_t = 64 * 1024
+    )]
+    pub max_body_size: usize,
+}
+
+impl Default for RateLimitOptions {
+    fn default() -> Self {
+        Self {
+            per_key_rate: 10.0,
+            per_key_burst: 20,
+            per_ip_rate: 50.0,
+            per_ip_burst: 100,
+            max_body_size: 64 * 1024,
+        }
+    }
+}
+
+/// Arguments for the operator API
+#[derive(Clone, Debug, Default, Parser)]
+pub struct AdminOptions {
+    /// BLS public keys, in tagged base64, whose signatures authorize admin requests
+    #[clap(
+        long = "operator-key",
+        env = "MARKETPLACE_SOLVER_OPERATOR_KEYS",
+        value_delimiter = ','
+    )]
+    pub operator_keys: Vec<BLSPubKey>,
 }
 
 /// Arguments for establishing a database connection
@@ -65,6 +234,121 @@ impl DatabaseOptions {
     }
 }
 
+/// Arguments for bid acceptance and winner determination in the auction
+#[derive(Clone, Debug, Parser)]
+pub struct AuctionOptions {
+    /// Bid books with at most this many eligible bids are solved with an exact search
+    #[clap(
+        long,
+        env = "MARKETPLACE_SOLVER_AUCTION_EXACT_SEARCH_LIMIT",
+        default_value_t = 20
+    )]
+    pub exact_search_limit: usize,
+
+    /// Time budget of the greedy approximation used for larger bid books
+    #[clap(
+        long,
+        value_parser = parse_duration,
+        env = "MARKETPLACE_SOLVER_AUCTION_GREEDY_TIME_BUDGET",
+        default_value = "100ms"
+    )]
+    pub greedy_time_budget: Duration,
+
+    /// How many views past the last finalized view bids are accepted for
+    #[clap(
+        long,
+        env = "MARKETPLACE_SOLVER_AUCTION_MAX_VIEW_LOOKAHEAD",
+        default_value_t = 1000
+    )]
+    pub max_view_lookahead: u64,
+
+    /// Upper bound on the amount of a single bid, in Wei
+    #[clap(long, env = "MARKETPLACE_SOLVER_AUCTION_MAX_BID_AMOUNT")]
+    pub max_bid_amount: Option<FeeAmount>,
+
+    /// How many distinct bids a builder may submit for a single view
+    #[clap(
+        long,
+        env = "MARKETPLACE_SOLVER_AUCTION_MAX_BIDS_PER_BUILDER",
+        default_value_t = 16
+    )]
+    pub max_bids_per_builder: usize,
+
+    /// Only accept bids from builders that registered with the solver
+    #[clap(
+        long,
+        env = "MARKETPLACE_SOLVER_REQUIRE_BUILDER_REGISTRATION",
+        default_value_t = false
+    )]
+    pub require_builder_registration: bool,
+
+    /// If not empty, only these builder accounts may bid. Use the admin API to ban builders while
+    /// the solver runs.
+    #[clap(
+        long,
+        env = "MARKETPLACE_SOLVER_BUILDER_ALLOW_LIST",
+        value_delimiter = ','
+    )]
+    pub builder_allow_list: Vec<FeeAccount>,
+
+    /// Builder accounts that may not bid or register
+    #[clap(
+        long,
+        env = "MARKETPLACE_SOLVER_BUILDER_DENY_LIST",
+        value_delimiter = ','
+    )]
+    pub builder_deny_list: Vec<FeeAccount>,
+}
+
+impl Default for AuctionOptions {
+    fn default() -> Self {
+        Self {
+            exact_search_limit: 20,
+            greedy_time_budget: Duration::from_millis(100),
+            max_view_lookahead: 1000,
+            max_bid_amount: None,
+            max_bids_per_builder: 16,
+            require_builder_registration: false,
+            builder_allow_list: Vec::new(),
+            builder_deny_list: Vec::new(),
+        }
+    }
+}
+
+/// Environment variable holding the solver's BLS private key, in tagged base64
+pub const SOLVER_PRIVATE_KEY_ENV: &str = "MARKETPLACE_SOLVER_PRIVATE_KEY";
+
+/// Arguments for loading the key the solver signs auction results with
+///
+/// The key itself is not accepted on the command line, where other users of the host can read
+/// it from the process list: it is read from a file or from [`SOLVER_PRIVATE_KEY_ENV`].
+#[derive(Clone, Parser)]
+pub struct SolverKeyOptions {
+    /// BLS private key, for embedding the solver. Otherwise it is read from
+    /// [`SOLVER_PRIVATE_KEY_ENV`] when the key is loaded.
+    #[clap(skip)]
+    pub private_key: Option<BLSPrivKey>,
+
+    /// File containing the BLS private key, in tagged base64
+    #[clap(
+        long = "solver-private-key-file",
+        env = "MARKETPLACE_SOLVER_PRIVATE_KEY_FILE"
+    )]
+    pub private_key_file: Option<PathBuf>,
+}
+
+impl fmt::Debug for SolverKeyOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SolverKeyOptions")
+            .field(
+                "private_key",
+                &self.private_key.as_ref().map(|_| "<redacted>"),
+            )
+            .field("private_key_file", &self.private_key_file)
+            .finish()
+    }
+}
+
 #[derive(Clone, Debug, Error)]
 #[error("failed to parse `{0}`")]
 pub struct ParseDurationError(String);
@@ -0,0 +1,170 @@
+//! Recordings of HotShot event streams.
+//!
+//! A recording is a bincode encoded [`StartupInfo`] followed by one [`RecordedEvent`] per event,
+//! in the order they were received. The mock events service can serve a recording in place of
+//! its generated events, so tests that depend on the timing of views are deterministic.
+use std::{
+    io::{Read, Write},
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use espresso_types::SeqTypes;
+use futures::StreamExt;
+use hotshot::types::Event;
+use hotshot_events_service::events_source::StartupInfo;
+use serde::{Deserialize, Serialize};
+
+use crate::EventsServiceClient;
+
+/// An event together with the time it was received at.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound(deserialize = ""))]
+pub struct RecordedEvent {
+    /// Time since the recording started
+    pub offset: Duration,
+    pub event: Event<SeqTypes>,
+}
+
+/// Writes an event stream to a recording.
+pub struct EventRecorder<W: Write> {
+    writer: W,
+    started: Instant,
+    count: usize,
+}
+
+impl<W: Write> EventRecorder<W> {
+    pub fn new(mut writer: W, startup_info: &StartupInfo<SeqTypes>) -> anyhow::Result<Self> {
+        bincode::serialize_into(&mut writer, startup_info)
+            .context("failed to write startup info")?;
+
+        Ok(Self {
+            writer,
+            started: Instant::now(),
+            count: 0,
+        })
+    }
+
+    pub fn record(&mut self, event: Event<SeqTypes>) -> anyhow::Result<()> {
+        let recorded = RecordedEvent {
+            offset: self.started.elapsed(),
+            event,
+        };
+        bincode::serialize_into(&mut self.writer, &recorded).context("failed to write event")?;
+        self.count += 1;
+
+        Ok(())
+    }
+
+    /// Number of events recorded so far.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn finish(mut self) -> anyhow::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Reads a recording written by [`EventRecorder`].
+pub fn read_recording(
+    mut reader: impl Read,
+) -> anyhow::Result<(StartupInfo<SeqTypes>, Vec<RecordedEvent>)> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    let mut remaining = bytes.as_slice();
+
+    let startup_info =
+        bincode::deserialize_from(&mut remaining).context("failed to read startup info")?;
+
+    let mut events = Vec::new();
+    while !remaining.is_empty() {
+        let event = bincode::deserialize_from(&mut remaining)
+            .with_context(|| format!("failed to read event {}", events.len()))?;
+        events.push(event);
+    }
+
+    Ok((startup_info, events))
+}
+
+/// Records the events of the events service behind `client`, until the stream ends or `limit`
+/// events were recorded. Returns the number of recorded events.
+pub async fn record_events<W: Write>(
+    client: EventsServiceClient,
+    writer: W,
+    limit: Option<usize>,
+) -> anyhow::Result<usize> {
+    let startup_info = client
+        .get_startup_info()
+        .await
+        .context("failed to get startup info")?;
+
+    let mut recorder = EventRecorder::new(writer, &startup_info)?;
+    let mut stream = client.get_event_stream().await?;
+
+    while limit.map_or(true, |limit| recorder.count() < limit) {
+        let Some(event) = stream.next().await else {
+            break;
+        };
+        recorder.record(event?)?;
+    }
+
+    let count = recorder.count();
+    recorder.finish()?;
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod test {
+    use espresso_types::SeqTypes;
+    use hotshot_events_service::events_source::StartupInfo;
+    use hotshot_types::{
+        data::ViewNumber,
+        event::{Event, EventType},
+        traits::node_implementation::ConsensusTime,
+    };
+
+    use super::{read_recording, EventRecorder};
+    use crate::mock::generate_stake_table;
+
+    #[test]
+    fn test_recording_round_trip() {
+        let startup_info = StartupInfo::<SeqTypes> {
+            known_node_with_stake: generate_stake_table(),
+            non_staked_node_count: 10,
+        };
+
+        let mut recorder = EventRecorder::new(Vec::new(), &startup_info).unwrap();
+        for view in 1..=5 {
+            recorder
+                .record(Event {
+                    view_number: ViewNumber::new(view + 1),
+                    event: EventType::ViewFinished {
+                        view_number: ViewNumber::new(view),
+                    },
+                })
+                .unwrap();
+        }
+        let bytes = recorder.finish().unwrap();
+
+        let (read_info, events) = read_recording(bytes.as_slice()).unwrap();
+        assert_eq!(
+            read_info.known_node_with_stake.len(),
+            startup_info.known_node_with_stake.len()
+        );
+        assert_eq!(events.len(), 5);
+        assert!(events.windows(2).all(|w| w[0].offset <= w[1].offset));
+
+        for (view, recorded) in (1..=5).zip(&events) {
+            assert!(matches!(
+                recorded.event.event,
+                EventType::ViewFinished { view_number } if view_number == ViewNumber::new(view)
+            ));
+        }
+
+        // A truncated event is an error rather than the end of the recording
+        read_recording(&bytes[..bytes.len() - 1]).unwrap_err();
+    }
+}
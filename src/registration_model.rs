@@ -0,0 +1,436 @@
+#![cfg(all(test, not(target_os = "windows")))]
+//! Property tests of rollup registrations against a reference model.
+//!
+//! Operations are generated over a small pool of keys and namespaces, so that sequences register
+//! namespaces twice, update namespaces that were never registered and sign with keys missing from
+//! `signature_keys`. The model applies the registration rules to plain bodies, and after every
+//! operation the registrations of the solver are compared with it. The same sequences are run
+//! through the solver state over each storage backend here, and through the HTTP API in
+//! `testing`.
+use std::{
+    collections::BTreeMap,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        OnceLock,
+    },
+};
+
+use async_std::task::block_on;
+use async_trait::async_trait;
+use espresso_types::{
+    v0_3::{RollupRegistration, RollupRegistrationBody, RollupUpdate, RollupUpdatebody},
+    NamespaceId,
+};
+use hotshot::types::{BLSPubKey, SignatureKey};
+use hotshot_types::signature_key::BLSPrivKey;
+use proptest::{
+    collection::vec,
+    option,
+    prelude::*,
+    test_runner::{Config, TestCaseError, TestRunner},
+};
+use tide_disco::Url;
+
+use crate::{
+    client::{sign_registration, sign_update, SolverClient},
+    state::{GlobalState, UpdateSolverState},
+    SolverError, SolverResult,
+};
+
+/// Size of the pool signature keys are drawn from
+const KEYS: u64 = 4;
+
+/// Namespaces the operations of a sequence refer to, before they are shifted
+const NAMESPACES: u64 = 3;
+
+fn keys() -> &'static [(BLSPubKey, BLSPrivKey)] {
+    static KEYS_POOL: OnceLock<Vec<(BLSPubKey, BLSPrivKey)>> = OnceLock::new();
+    KEYS_POOL.get_or_init(|| {
+        (0..KEYS)
+            .map(|index| BLSPubKey::generated_from_seed_indexed([0; 32], index))
+            .collect()
+    })
+}
+
+fn public_key(index: u64) -> BLSPubKey {
+    keys()[index as usize].0
+}
+
+/// Private key of `key`, or of another key of the pool if `forged`.
+fn private_key(key: &BLSPubKey, forged: bool) -> &'static BLSPrivKey {
+    let index = keys()
+        .iter()
+        .position(|(public, _)| public == key)
+        .expect("key is not from the pool");
+    let index = if forged {
+        (index + 1) % keys().len()
+    } else {
+        index
+    };
+
+    &keys()[index].1
+}
+
+fn reserve_url() -> impl Strategy<Value = Url> {
+    (0..3_u8).prop_map(|i| Url::from_str(&format!("http://reserve-{i}")).unwrap())
+}
+
+/// A signing key and a list of signature keys, which usually contains it.
+fn signing_keys() -> impl Strategy<Value = (BLSPubKey, Vec<BLSPubKey>)> {
+    (0..KEYS, vec(0..KEYS, 0..=3), proptest::bool::weighted(0.75)).prop_map(
+        |(signer, keys, include_signer)| {
+            let mut keys: Vec<_> = keys.into_iter().map(public_key).collect();
+            if include_signer && !keys.contains(&public_key(signer)) {
+                keys.push(public_key(signer));
+            }
+
+            (public_key(signer), keys)
+        },
+    )
+}
+
+pub fn registration_body() -> impl Strategy<Value = RollupRegistrationBody> {
+    (
+        1..=NAMESPACES,
+        reserve_url(),
+        0..1000_u64,
+        any::<bool>(),
+        "[a-z]{0,8}",
+        signing_keys(),
+    )
+        .prop_map(
+            |(namespace_id, reserve_url, reserve_price, active, text, (signature_key, keys))| {
+                RollupRegistrationBody {
+                    namespace_id: namespace_id.into(),
+                    reserve_url,
+                    reserve_price: reserve_price.into(),
+                    active,
+                    signature_keys: keys,
+                    text,
+                    signature_key,
+                }
+            },
+        )
+}
+
+pub fn update_body() -> impl Strategy<Value = RollupUpdatebody> {
+    (
+        1..=NAMESPACES,
+        option::of(reserve_url()),
+        option::of(0..1000_u64),
+        option::of(any::<bool>()),
+        option::of("[a-z]{0,8}"),
+        signing_keys(),
+        any::<bool>(),
+    )
+        .prop_map(
+            |(
+                namespace_id,
+                reserve_url,
+                reserve_price,
+                active,
+                text,
+                (signature_key, keys),
+                replace_keys,
+            )| RollupUpdatebody {
+                namespace_id: namespace_id.into(),
+                reserve_url,
+                reserve_price: reserve_price.map(Into::into),
+                active,
+                signature_keys: replace_keys.then_some(keys),
+                signature_key,
+                text,
+            },
+        )
+}
+
+#[derive(Clone, Debug)]
+pub enum Op {
+    Register {
+        body: RollupRegistrationBody,
+        /// Signed with a key other than `body.signature_key`
+        forged: bool,
+    },
+    Update {
+        body: RollupUpdatebody,
+        forged: bool,
+    },
+}
+
+impl Op {
+    /// Moves the operation to namespace `offset + namespace_id`, so that sequences run against
+    /// the same solver do not interfere.
+    fn shifted(mut self, offset: u64) -> Self {
+        let namespace_id = match &mut self {
+            Self::Register { body, .. } => &mut body.namespace_id,
+            Self::Update { body, .. } => &mut body.namespace_id,
+        };
+        *namespace_id = (u64::from(*namespace_id) + offset).into();
+
+        self
+    }
+}
+
+pub fn operation() -> impl Strategy<Value = Op> {
+    let forged = || proptest::bool::weighted(0.1);
+
+    prop_oneof![
+        (registration_body(), forged()).prop_map(|(body, forged)| Op::Register { body, forged }),
+        (update_body(), forged()).prop_map(|(body, forged)| Op::Update { body, forged }),
+    ]
+}
+
+pub fn operations() -> impl Strategy<Value = Vec<Op>> {
+    vec(operation(), 1..24)
+}
+
+/// What became of an operation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Accepted,
+    InvalidSignature,
+    KeysMismatch,
+    AlreadyExists,
+    NotFound,
+}
+
+impl Outcome {
+    fn of(result: &SolverResult<RollupRegistration>) -> Self {
+        match result {
+            Ok(_) => Self::Accepted,
+            Err(SolverError::InvalidSignature(_)) => Self::InvalidSignature,
+            Err(SolverError::SignatureKeysMismatch(_)) => Self::KeysMismatch,
+            Err(SolverError::RollupAlreadyExists(_)) => Self::AlreadyExists,
+            Err(SolverError::RollupNotFound(_)) => Self::NotFound,
+            Err(err) => panic!("unexpected error {err:?}"),
+        }
+    }
+}
+
+/// The registrations the solver should hold.
+#[derive(Clone, Debug, Default)]
+pub struct Model {
+    pub registrations: BTreeMap<NamespaceId, RollupRegistrationBody>,
+}
+
+impl Model {
+    pub fn apply(&mut self, op: &Op) -> Outcome {
+        match op {
+            Op::Register { body, forged } => {
+                if !body.signature_keys.contains(&body.signature_key) {
+                    return Outcome::KeysMismatch;
+                }
+                if *forged {
+                    return Outcome::InvalidSignature;
+                }
+                if self.registrations.contains_key(&body.namespace_id) {
+                    return Outcome::AlreadyExists;
+                }
+
+                self.registrations.insert(body.namespace_id, body.clone());
+                Outcome::Accepted
+            }
+            Op::Update { body, forged } => {
+                if *forged {
+                    return Outcome::InvalidSignature;
+                }
+                let Some(registration) = self.registrations.get_mut(&body.namespace_id) else {
+                    return Outcome::NotFound;
+                };
+
+                // The signing key must be authorized both before and after the update
+                if !registration.signature_keys.contains(&body.signature_key) {
+                    return Outcome::KeysMismatch;
+                }
+                if let Some(keys) = &body.signature_keys {
+                    if !keys.contains(&body.signature_key) {
+                        return Outcome::KeysMismatch;
+                    }
+                }
+
+                // Fields left out of the update keep their value
+                let mut updated = registration.clone();
+                if let Some(reserve_url) = &body.reserve_url {
+                    updated.reserve_url = reserve_url.clone();
+                }
+                if let Some(reserve_price) = body.reserve_price {
+                    updated.reserve_price = reserve_price;
+                }
+                if let Some(active) = body.active {
+                    updated.active = active;
+                }
+                if let Some(text) = &body.text {
+                    updated.text = text.clone();
+                }
+                if let Some(keys) = &body.signature_keys {
+                    updated.signature_keys = keys.clone();
+                }
+
+                *registration = updated;
+                Outcome::Accepted
+            }
+        }
+    }
+}
+
+/// A solver the operations are run against.
+#[async_trait]
+pub trait Registrations: Sync {
+    async fn register(&self, registration: RollupRegistration) -> SolverResult<RollupRegistration>;
+    async fn update(&self, update: RollupUpdate) -> SolverResult<RollupRegistration>;
+    async fn registrations(&self) -> SolverResult<Vec<RollupRegistration>>;
+}
+
+#[async_trait]
+impl Registrations for GlobalState {
+    async fn register(&self, registration: RollupRegistration) -> SolverResult<RollupRegistration> {
+        self.register_rollup(registration).await
+    }
+
+    async fn update(&self, update: RollupUpdate) -> SolverResult<RollupRegistration> {
+        self.update_rollup_registration(update).await
+    }
+
+    async fn registrations(&self) -> SolverResult<Vec<RollupRegistration>> {
+        self.get_all_rollup_registrations().await
+    }
+}
+
+#[async_trait]
+impl Registrations for SolverClient {
+    async fn register(&self, registration: RollupRegistration) -> SolverResult<RollupRegistration> {
+        self.register_rollup(&registration).await
+    }
+
+    async fn update(&self, update: RollupUpdate) -> SolverResult<RollupRegistration> {
+        self.update_rollup(&update).await
+    }
+
+    async fn registrations(&self) -> SolverResult<Vec<RollupRegistration>> {
+        self.rollup_registrations().await
+    }
+}
+
+/// The registered bodies of the namespaces in `offset + 1..=offset + NAMESPACES`.
+pub fn shifted_bodies(
+    registrations: Vec<RollupRegistration>,
+    offset: u64,
+) -> BTreeMap<NamespaceId, RollupRegistrationBody> {
+    registrations
+        .into_iter()
+        .filter(|r| (offset + 1..=offset + NAMESPACES).contains(&u64::from(r.body.namespace_id)))
+        .map(|r| (r.body.namespace_id, r.body))
+        .collect()
+}
+
+/// Runs `ops` against `solver` on namespaces shifted by `offset` and checks every result and the
+/// registrations after every operation against the model, which is returned.
+pub async fn check(
+    solver: &impl Registrations,
+    ops: Vec<Op>,
+    offset: u64,
+) -> Result<Model, TestCaseError> {
+    let mut model = Model::default();
+
+    for op in ops {
+        let op = op.shifted(offset);
+        let expected = model.apply(&op);
+
+        let result = match &op {
+            Op::Register { body, forged } => {
+                let registration =
+                    sign_registration(body.clone(), private_key(&body.signature_key, *forged))
+                        .expect("failed to sign registration");
+                solver.register(registration).await
+            }
+            Op::Update { body, forged } => {
+                let update = sign_update(body.clone(), private_key(&body.signature_key, *forged))
+                    .expect("failed to sign update");
+                solver.update(update).await
+            }
+        };
+
+        prop_assert_eq!(Outcome::of(&result), expected, "{:?}: {:?}", op, result);
+        if let Ok(registration) = result {
+            prop_assert_eq!(
+                Some(&registration.body),
+                model.registrations.get(&registration.body.namespace_id)
+            );
+        }
+
+        let registrations = solver
+            .registrations()
+            .await
+            .map_err(|err| TestCaseError::fail(err.to_string()))?;
+        prop_assert_eq!(&shifted_bodies(registrations, offset), &model.registrations);
+    }
+
+    Ok(model)
+}
+
+/// Checks `cases` generated sequences against `solver`, each on namespaces of its own.
+pub fn run(solver: &impl Registrations, cases: u32, after: impl Fn(&Model, u64)) {
+    let offset = AtomicU64::new(0);
+    let mut runner = TestRunner::new(Config {
+        cases,
+        failure_persistence: None,
+        ..Config::default()
+    });
+
+    runner
+        .run(&operations(), |ops| {
+            let offset = offset.fetch_add(NAMESPACES, Ordering::SeqCst);
+            let model = block_on(check(solver, ops, offset))?;
+            after(&model, offset);
+            Ok(())
+        })
+        .unwrap();
+}
+
+mod test {
+    use std::sync::Arc;
+
+    use async_std::task::block_on;
+
+    use super::{run, shifted_bodies, Model};
+    use crate::{
+        signing::SolverKey,
+        state::{GlobalState, SolverState},
+        storage::{mock::setup_mock_storage, MemoryStorage, SolverStorage},
+    };
+
+    fn state(storage: Arc<dyn SolverStorage>) -> GlobalState {
+        GlobalState::new(
+            storage,
+            SolverState::mock(),
+            Default::default(),
+            SolverKey::generate(),
+        )
+        .unwrap()
+    }
+
+    /// The registrations in storage match the ones the state serves from its cache.
+    fn check_storage(storage: &dyn SolverStorage) -> impl Fn(&Model, u64) + '_ {
+        move |model, offset| {
+            let registrations = block_on(storage.get_all_rollup_registrations()).unwrap();
+            assert_eq!(shifted_bodies(registrations, offset), model.registrations);
+        }
+    }
+
+    #[test]
+    fn test_registrations_in_memory() {
+        let storage = Arc::new(MemoryStorage::new());
+        let state = state(storage.clone());
+
+        run(&state, 128, check_storage(storage.as_ref()));
+    }
+
+    #[test]
+    fn test_registrations_over_storage() {
+        let (_tmp_db, storage) = block_on(setup_mock_storage());
+        let state = state(storage.clone());
+
+        run(&state, 16, check_storage(storage.as_ref()));
+    }
+}
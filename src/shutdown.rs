@@ -0,0 +1,62 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Context;
+use async_std::{future::timeout, task::JoinHandle};
+use futures::StreamExt;
+use signal_hook::consts::{SIGINT, SIGTERM};
+use signal_hook_async_std::Signals;
+
+use crate::state::GlobalState;
+
+/// Waits until the process receives SIGTERM or SIGINT.
+pub async fn wait_for_signal() -> anyhow::Result<()> {
+    let mut signals =
+        Signals::new([SIGTERM, SIGINT]).context("failed to register signal handlers")?;
+    let handle = signals.handle();
+
+    if let Some(signal) = signals.next().await {
+        tracing::info!("received signal {signal}");
+    }
+
+    handle.close();
+    Ok(())
+}
+
+/// Shuts the solver down, giving in-flight work at most `deadline` to finish.
+///
+/// New bids are rejected first, then the auction of the current view is finalized and persisted.
+/// Only after that are the events subscription and the database pool closed, so the flushed
+/// outcome is not lost. The HTTP server should already be stopped and drained, see
+/// [`ListenerHandle`](crate::listener::ListenerHandle).
+pub async fn shutdown(
+    state: Arc<GlobalState>,
+    event_handler: JoinHandle<()>,
+    deadline: Duration,
+) -> anyhow::Result<()> {
+    let result = timeout(deadline, async {
+        state.stop_accepting_bids();
+
+        let flushed = state.flush_auction().await;
+
+        event_handler.cancel().await;
+        state.close_storage().await;
+
+        match flushed? {
+            Some(outcome) => {
+                tracing::info!(
+                    "finalized auction for view {:?} before shutdown",
+                    outcome.inputs.view_number
+                )
+            }
+            None => tracing::info!("no open auction to finalize before shutdown"),
+        }
+
+        Ok(())
+    })
+    .await;
+
+    match result {
+        Ok(result) => result,
+        Err(_) => anyhow::bail!("shutdown did not finish within {deadline:?}"),
+    }
+}
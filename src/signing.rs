@@ -0,0 +1,247 @@
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::{
+    env,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::Path,
+    str::FromStr,
+};
+
+use anyhow::{bail, Context};
+use committable::{Commitment, Committable, RawCommitmentBuilder};
+use espresso_types::{v0_3::SolverAuctionResults, SeqTypes};
+use hotshot::types::{BLSPubKey, SignatureKey};
+use hotshot_types::{signature_key::BLSPrivKey, traits::node_implementation::NodeType};
+use serde::{Deserialize, Serialize};
+use tide_disco::StatusCode;
+
+use crate::{SolverError, SolverKeyOptions, SolverResult, SOLVER_PRIVATE_KEY_ENV};
+
+pub type SolverSignature = <BLSPubKey as SignatureKey>::PureAssembledSignatureType;
+
+/// Key pair the solver signs its auction results with.
+#[derive(Clone)]
+pub struct SolverKey {
+    private_key: BLSPrivKey,
+    public_key: BLSPubKey,
+}
+
+impl SolverKey {
+    pub fn new(private_key: BLSPrivKey) -> Self {
+        let public_key = BLSPubKey::from_private(&private_key);
+
+        Self {
+            private_key,
+            public_key,
+        }
+    }
+
+    pub fn generate() -> Self {
+        Self::new(BLSPrivKey::generate(&mut rand::thread_rng()))
+    }
+
+    pub fn public_key(&self) -> BLSPubKey {
+        self.public_key
+    }
+
+    /// Signs the commitment of the results of a finalized auction.
+    pub fn sign(&self, results: SolverAuctionResults) -> SolverResult<SignedAuctionResults> {
+        self.sign_results(results, false)
+    }
+
+    /// Signs the results of an auction that is still open, see
+    /// [`SignedAuctionResults::provisional`].
+    pub fn sign_provisional(
+        &self,
+        results: SolverAuctionResults,
+    ) -> SolverResult<SignedAuctionResults> {
+        self.sign_results(results, true)
+    }
+
+    fn sign_results(
+        &self,
+        results: SolverAuctionResults,
+        provisional: bool,
+    ) -> SolverResult<SignedAuctionResults> {
+        let signature = <SeqTypes as NodeType>::SignatureKey::sign(
+            &self.private_key,
+            signed_commitment(&results, provisional).as_ref(),
+        )
+        .map_err(|err| SolverError::Custom {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            message: format!("failed to sign auction results: {err}"),
+        })?;
+
+        Ok(SignedAuctionResults {
+            results,
+            provisional,
+            signature,
+        })
+    }
+}
+
+/// Auction results together with the solver's signature over their commitment.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedAuctionResults {
+    pub results: SolverAuctionResults,
+    /// Whether the auction was still open, so the results may differ from the finalized ones.
+    /// The signature of provisional results covers a commitment with its own tag, so they cannot
+    /// be passed off as finalized results.
+    #[serde(default)]
+    pub provisional: bool,
+    pub signature: SolverSignature,
+}
+
+impl SignedAuctionResults {
+    /// Checks that the results were signed by the solver with the public key `solver_key`.
+    pub fn verify(&self, solver_key: &BLSPubKey) -> bool {
+        <SeqTypes as NodeType>::SignatureKey::validate(
+            solver_key,
+            &self.signature,
+            signed_commitment(&self.results, self.provisional).as_ref(),
+        )
+    }
+}
+
+fn signed_commitment(
+    results: &SolverAuctionResults,
+    provisional: bool,
+) -> Commitment<SolverAuctionResults> {
+    if provisional {
+        RawCommitmentBuilder::new("PROVISIONAL_AUCTION_RESULTS")
+            .field("results", results.commit())
+            .finalize()
+    } else {
+        results.commit()
+    }
+}
+
+/// Reads a BLS private key in tagged base64 from the file at `path`.
+pub fn read_private_key(path: &Path) -> anyhow::Result<BLSPrivKey> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+
+    BLSPrivKey::from_str(contents.trim())
+        .map_err(|err| anyhow::anyhow!("invalid private key in {}: {err}", path.display()))
+}
+
+/// Writes a BLS private key in tagged base64 to a new file at `path`, which only its owner may
+/// read. Fails if the file exists, rather than replacing a key that may still be in use.
+pub fn write_private_key(path: &Path, private_key: &BLSPrivKey) -> anyhow::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("failed to create {}", path.display()))?;
+    writeln!(file, "{private_key}").with_context(|| format!("failed to write {}", path.display()))
+}
+
+impl SolverKeyOptions {
+    pub fn load(self) -> anyhow::Result<SolverKey> {
+        let private_key = match self.private_key {
+            Some(private_key) => Some(private_key),
+            None => env::var(SOLVER_PRIVATE_KEY_ENV)
+                .ok()
+                .map(|key| BLSPrivKey::from_str(key.trim()))
+                .transpose()
+                .map_err(|err| anyhow::anyhow!("invalid {SOLVER_PRIVATE_KEY_ENV}: {err}"))?,
+        };
+
+        let private_key = match (private_key, self.private_key_file) {
+            (Some(private_key), None) => private_key,
+            (None, Some(path)) => read_private_key(&path)?,
+            (Some(_), Some(_)) => bail!("provide either a solver private key or a key file"),
+            (None, None) => bail!("solver private key not provided"),
+        };
+
+        Ok(SolverKey::new(private_key))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use clap::Parser;
+    use espresso_types::v0_3::SolverAuctionResults;
+    use hotshot_types::{data::ViewNumber, traits::node_implementation::ConsensusTime};
+    use tide_disco::Url;
+
+    use super::{read_private_key, write_private_key, SolverKey};
+    use crate::SolverKeyOptions;
+
+    #[test]
+    fn test_sign_auction_results() {
+        let key = SolverKey::generate();
+
+        let results = SolverAuctionResults::new(
+            ViewNumber::new(1),
+            Vec::new(),
+            vec![(1_u64.into(), Url::from_str("http://reserve").unwrap())],
+        );
+
+        let mut signed = key.sign(results.clone()).unwrap();
+        assert!(signed.verify(&key.public_key()));
+
+        // Results of an open auction cannot pass for finalized ones
+        let mut provisional = key.sign_provisional(results).unwrap();
+        assert!(provisional.verify(&key.public_key()));
+        assert_ne!(provisional.signature, signed.signature);
+        provisional.provisional = false;
+        assert!(!provisional.verify(&key.public_key()));
+
+        // Signatures don't verify against another key
+        assert!(!signed.verify(&SolverKey::generate().public_key()));
+
+        // Nor for modified results
+        signed.results = SolverAuctionResults::new(ViewNumber::new(2), Vec::new(), Vec::new());
+        assert!(!signed.verify(&key.public_key()));
+    }
+
+    #[test]
+    fn test_private_key_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("key");
+        let key = SolverKey::generate();
+
+        write_private_key(&path, &key.private_key).unwrap();
+        let read = read_private_key(&path).unwrap();
+        assert_eq!(SolverKey::new(read).public_key(), key.public_key());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        // An existing key is never overwritten
+        write_private_key(&path, &SolverKey::generate().private_key).unwrap_err();
+        let read = read_private_key(&path).unwrap();
+        assert_eq!(SolverKey::new(read).public_key(), key.public_key());
+    }
+
+    #[test]
+    fn test_solver_key_options() {
+        let key = SolverKey::generate();
+        let options = SolverKeyOptions {
+            private_key: Some(key.private_key.clone()),
+            private_key_file: None,
+        };
+
+        // The key never shows up in logs
+        let debug = format!("{options:?}");
+        assert!(!debug.contains(&key.private_key.to_string()), "{debug}");
+        assert!(debug.contains("<redacted>"), "{debug}");
+
+        assert_eq!(options.load().unwrap().public_key(), key.public_key());
+
+        // Nor on the command line
+        SolverKeyOptions::try_parse_from(["solver", "--solver-private-key", "key"]).unwrap_err();
+    }
+}
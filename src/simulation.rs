@@ -0,0 +1,300 @@
+//! Offline replay of recorded auction traffic.
+//!
+//! Recorded bids and registrations are fed through [`compute_auction_results`], the same code
+//! the solver finalizes auctions with, so changes to the auction rules can be evaluated against
+//! real traffic without an events service.
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::BufRead,
+};
+
+use anyhow::Context;
+use espresso_types::{
+    v0_3::{BidTx, RollupRegistration},
+    FeeAccount, FeeAmount, NamespaceId,
+};
+use hotshot_types::data::ViewNumber;
+use serde::{Deserialize, Serialize};
+use tide_disco::Url;
+
+use crate::{
+    auction::{compute_auction_results, AuctionInputs, AuctionOutcome, WinnerDetermination},
+    storage::SolverStorage,
+    AuctionOptions,
+};
+
+/// A single entry of a recorded JSONL traffic file.
+///
+/// Records take effect in file order, as if they arrived at the solver in that order.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrafficRecord {
+    /// A rollup registration, or the registration resulting from an update
+    Registration(RollupRegistration),
+    /// Removal of the registration of a namespace
+    Deregistration(NamespaceId),
+    Bid(BidTx),
+    /// The auction for a view is finalized with the bids received so far
+    Finalize(ViewNumber),
+}
+
+/// Reads traffic records from a JSONL file, skipping blank lines.
+pub fn read_traffic(reader: impl BufRead) -> anyhow::Result<Vec<TrafficRecord>> {
+    let mut records = Vec::new();
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let record = serde_json::from_str(&line)
+            .with_context(|| format!("invalid traffic record on line {}", i + 1))?;
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+/// Loads the inputs and results of every finalized auction from the solver's storage.
+pub async fn load_recorded_auctions(
+    storage: &dyn SolverStorage,
+) -> anyhow::Result<Vec<AuctionOutcome>> {
+    storage
+        .get_all_auction_outcomes()
+        .await
+        .context("failed to load recorded auctions")
+}
+
+/// A winning bid of a simulated auction.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SimulatedWinner {
+    pub account: FeeAccount,
+    pub namespaces: Vec<NamespaceId>,
+    pub amount: FeeAmount,
+    pub url: Url,
+}
+
+/// The outcome of one simulated auction.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ViewReport {
+    pub view_number: ViewNumber,
+    pub bid_count: usize,
+    pub method: WinnerDetermination,
+    pub winners: Vec<SimulatedWinner>,
+    /// Sum of the winning bid amounts
+    pub revenue: FeeAmount,
+    /// Namespaces that fell back to their reserve builder
+    pub reserve_fallbacks: Vec<(NamespaceId, Url)>,
+    /// Whether the results differ from the recorded ones, if the auction was recorded
+    pub changed: Option<bool>,
+}
+
+impl ViewReport {
+    fn new(outcome: &AuctionOutcome) -> Self {
+        let winners: Vec<SimulatedWinner> = outcome
+            .results
+            .winning_bids()
+            .iter()
+            .map(|bid| SimulatedWinner {
+                account: bid.account(),
+                namespaces: bid.namespaces().to_vec(),
+                amount: bid.amount(),
+                url: bid.url(),
+            })
+            .collect();
+
+        let revenue = winners.iter().fold(FeeAmount::from(0_u64), |sum, winner| {
+            sum.checked_add(winner.amount)
+                .expect("winning amounts are bounded by the bid total")
+        });
+
+        Self {
+            view_number: outcome.inputs.view_number,
+            bid_count: outcome.inputs.bids.len(),
+            method: outcome.method,
+            winners,
+            revenue,
+            reserve_fallbacks: outcome.results.reserve_bids().to_vec(),
+            changed: None,
+        }
+    }
+}
+
+/// Replays recorded traffic view by view.
+///
+/// Bids are buffered per view until a [`TrafficRecord::Finalize`] for their view, which
+/// computes the auction from the buffered bids and the registrations at that point. Bids for a
+/// view that was already finalized are dropped, as the solver would reject them. Views still
+/// open at the end of the traffic are finalized in order.
+pub struct Simulation {
+    options: AuctionOptions,
+    registrations: BTreeMap<NamespaceId, RollupRegistration>,
+    bids: BTreeMap<ViewNumber, Vec<BidTx>>,
+    finalized: BTreeSet<ViewNumber>,
+    late_bids: usize,
+    reports: Vec<ViewReport>,
+}
+
+impl Simulation {
+    pub fn new(options: AuctionOptions) -> Self {
+        Self {
+            options,
+            registrations: BTreeMap::new(),
+            bids: BTreeMap::new(),
+            finalized: BTreeSet::new(),
+            late_bids: 0,
+            reports: Vec::new(),
+        }
+    }
+
+    pub fn apply(&mut self, record: TrafficRecord) {
+        match record {
+            TrafficRecord::Registration(registration) => {
+                self.registrations
+                    .insert(registration.body.namespace_id, registration);
+            }
+            TrafficRecord::Deregistration(namespace_id) => {
+                self.registrations.remove(&namespace_id);
+            }
+            TrafficRecord::Bid(bid) => {
+                if self.finalized.contains(&bid.view()) {
+                    self.late_bids += 1;
+                } else {
+                    self.bids.entry(bid.view()).or_default().push(bid);
+                }
+            }
+            TrafficRecord::Finalize(view_number) => self.finalize(view_number),
+        }
+    }
+
+    fn finalize(&mut self, view_number: ViewNumber) {
+        if !self.finalized.insert(view_number) {
+            return;
+        }
+
+        let bids = self.bids.remove(&view_number).unwrap_or_default();
+        let inputs = AuctionInputs::new(view_number, bids, self.registrations.values().cloned());
+        let outcome = compute_auction_results(inputs, &self.options);
+
+        self.reports.push(ViewReport::new(&outcome));
+    }
+
+    /// Bids dropped because their view was already finalized.
+    pub fn late_bids(&self) -> usize {
+        self.late_bids
+    }
+
+    /// Finalizes the views that are still open and returns the reports of every auction.
+    pub fn finish(mut self) -> Vec<ViewReport> {
+        let open: Vec<ViewNumber> = self.bids.keys().copied().collect();
+        for view_number in open {
+            self.finalize(view_number);
+        }
+
+        self.reports
+    }
+}
+
+/// Recomputes recorded auctions from their inputs with `options`.
+///
+/// Every auction sees exactly the bids and registrations it was originally computed from, so
+/// the reports show how the results would have changed under different auction rules.
+pub fn resolve_recorded(
+    outcomes: Vec<AuctionOutcome>,
+    options: &AuctionOptions,
+) -> Vec<ViewReport> {
+    outcomes
+        .into_iter()
+        .map(|recorded| {
+            let outcome = compute_auction_results(recorded.inputs, options);
+
+            let mut report = ViewReport::new(&outcome);
+            report.changed = Some(outcome.results != recorded.results);
+            report
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use espresso_types::FeeAmount;
+    use hotshot_types::{data::ViewNumber, traits::node_implementation::ConsensusTime};
+
+    use super::{read_traffic, resolve_recorded, Simulation, TrafficRecord};
+    use crate::{
+        auction::{compute_auction_results, AuctionInputs},
+        testing::{bid, registration},
+        AuctionOptions,
+    };
+
+    #[test]
+    fn test_replay_traffic() {
+        let cheap = registration(1, 100, true);
+        let mut expensive = cheap.clone();
+        expensive.body.reserve_price = 500.into();
+
+        let records = vec![
+            TrafficRecord::Registration(cheap.clone()),
+            TrafficRecord::Bid(bid(1, &[1], 300)),
+            TrafficRecord::Bid(bid(2, &[1], 300)),
+            TrafficRecord::Finalize(ViewNumber::new(1)),
+            // Too late for view 1
+            TrafficRecord::Bid(bid(1, &[1], 1000)),
+            // View 2 is finalized at the end with the raised reserve price
+            TrafficRecord::Registration(expensive),
+        ];
+
+        // Records survive the round trip through a JSONL file
+        let file: String = records
+            .iter()
+            .map(|record| serde_json::to_string(record).unwrap() + "\n")
+            .collect();
+        assert_eq!(read_traffic(file.as_bytes()).unwrap(), records);
+
+        let mut simulation = Simulation::new(AuctionOptions::default());
+        for record in records {
+            simulation.apply(record);
+        }
+        assert_eq!(simulation.late_bids(), 1);
+
+        let reports = simulation.finish();
+        assert_eq!(reports.len(), 2);
+
+        assert_eq!(reports[0].view_number, ViewNumber::new(1));
+        assert_eq!(reports[0].winners.len(), 1);
+        assert_eq!(reports[0].revenue, FeeAmount::from(300_u64));
+        assert!(reports[0].reserve_fallbacks.is_empty());
+
+        assert_eq!(reports[1].view_number, ViewNumber::new(2));
+        assert!(reports[1].winners.is_empty());
+        assert_eq!(reports[1].revenue, FeeAmount::from(0_u64));
+        assert_eq!(
+            reports[1].reserve_fallbacks,
+            vec![(1_u64.into(), cheap.body.reserve_url)]
+        );
+    }
+
+    #[test]
+    fn test_resolve_recorded() {
+        let mut registrations = vec![registration(1, 100, true)];
+        let bids = vec![bid(1, &[1], 300), bid(1, &[1], 200)];
+
+        let inputs = AuctionInputs::new(ViewNumber::new(1), bids.clone(), registrations.clone());
+        let recorded = compute_auction_results(inputs, &AuctionOptions::default());
+
+        let reports = resolve_recorded(vec![recorded.clone()], &AuctionOptions::default());
+        assert_eq!(reports[0].changed, Some(false));
+        assert_eq!(reports[0].revenue, FeeAmount::from(300_u64));
+
+        // A recorded auction whose results no longer match its inputs is reported as changed
+        registrations[0].body.reserve_price = 1000.into();
+        let inputs = AuctionInputs::new(ViewNumber::new(1), bids, registrations);
+        let mut stale = recorded;
+        stale.inputs = inputs;
+
+        let reports = resolve_recorded(vec![stale], &AuctionOptions::default());
+        assert_eq!(reports[0].changed, Some(true));
+        assert!(reports[0].winners.is_empty());
+    }
+}
@@ -1,28 +1,80 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, PoisonError, RwLock,
+    },
+    time::Duration,
+};
 
+use async_std::{future::timeout, sync::Mutex};
 use async_trait::async_trait;
-use committable::Committable;
+use committable::{Commitment, Committable, RawCommitmentBuilder};
 use espresso_types::{
-    v0_3::{
-        RollupRegistration, RollupRegistrationBody, RollupUpdate, RollupUpdatebody,
-        SolverAuctionResults,
-    },
-    PubKey, SeqTypes,
+    v0_3::{BidTx, RollupRegistration, RollupRegistrationBody, RollupUpdate, RollupUpdatebody},
+    FeeAccount, FeeAmount, NamespaceId, PubKey, SeqTypes,
+};
+use futures::{
+    future::{self, BoxFuture},
+    stream::{self, BoxStream, StreamExt},
 };
 use hotshot::types::SignatureKey;
 use hotshot_types::{
-    data::ViewNumber, signature_key::BuilderKey, traits::node_implementation::NodeType, PeerConfig,
+    data::ViewNumber,
+    signature_key::BLSPrivKey,
+    traits::node_implementation::{ConsensusTime, NodeType},
+    PeerConfig,
 };
+use prometheus::Registry;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
-use sqlx::{FromRow, PgPool};
+use tide_disco::{method::ReadState, Url};
 
-use crate::{database::PostgresClient, overflow_err, serde_json_err, SolverError, SolverResult};
+use crate::{
+    admin::{unix_time, AdminAction, AdminRequest, AuditLogEntry, SolverAdmin},
+    auction::{compute_auction_results, AuctionInputs, AuctionOutcome},
+    bid_book::BidBook,
+    builders::BuilderRegistration,
+    feeds::{Feed, FinalizedAuction, RegistrationChange, RegistrationChangeKind},
+    metrics::SolverMetrics,
+    overflow_err,
+    signing::{SignedAuctionResults, SolverKey},
+    storage::SolverStorage,
+    AuctionOptions, EventsHealth, Liveness, Readiness, SolverError, SolverResult, SolverStatus,
+};
 
-// TODO ED: Implement a shared solver state with the HotShot events received
+/// How long the readiness probe waits for the database
+const DATABASE_PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// State shared by the API handlers and the event handler.
+///
+/// Every operation takes `&self`, so requests are handled concurrently. Bids go into a sharded
+/// [`BidBook`] and are validated against in-memory copies of the registrations, bans and builder
+/// registrations, so the only storage access on submission is persisting the accepted bid. Only
+/// operations that read and then write the same data are serialized, each kind by its own lock.
 pub struct GlobalState {
     solver: SolverState,
-    database: PostgresClient,
+    storage: Arc<dyn SolverStorage>,
+    registrations: Cached<BTreeMap<NamespaceId, RollupRegistration>>,
+    banned_builders: Cached<HashSet<FeeAccount>>,
+    builder_registrations: Cached<HashMap<FeeAccount, BuilderRegistration>>,
+    // Persisted, so a paused solver stays paused across restarts
+    bidding_paused: Cached<bool>,
+    // Serializes registrations and updates, which check the stored registration first
+    registration_writes: Mutex<()>,
+    // Serializes builder registrations, so the cached copy ends up as the stored one
+    builder_registration_writes: Mutex<()>,
+    // Serializes finalizing auctions, so each is persisted and published once
+    finalization: Mutex<()>,
+    // Serializes admin requests, so each is executed at most once
+    admin_requests: Mutex<()>,
+    auction_options: AuctionOptions,
+    solver_key: SolverKey,
+    metrics: SolverMetrics,
+    events_health: Arc<EventsHealth>,
+    accepting_bids: AtomicBool,
+    finalized_auctions: Feed<FinalizedAuction>,
+    registration_changes: Feed<RegistrationChange>,
 }
 
 impl GlobalState {
@@ -30,35 +82,417 @@ impl GlobalState {
         &self.solver
     }
 
-    pub fn database(&self) -> &PgPool {
-        self.database.pool()
+    pub fn storage(&self) -> &dyn SolverStorage {
+        self.storage.as_ref()
+    }
+
+    pub fn auction_options(&self) -> &AuctionOptions {
+        &self.auction_options
+    }
+
+    pub fn metrics(&self) -> &SolverMetrics {
+        &self.metrics
+    }
+
+    pub fn events_health(&self) -> Arc<EventsHealth> {
+        self.events_health.clone()
+    }
+
+    /// Opens bidding from `view_number`, the current view of the network, on.
+    ///
+    /// Until then bids are only accepted for views up to the lookahead from view zero, which the
+    /// network has long passed. Bids stored for the open views, e.g. before the solver restarted,
+    /// are added back to their auctions.
+    pub async fn open_bidding_from(&self, view_number: ViewNumber) -> SolverResult<()> {
+        self.solver.bid_txs.open_from(view_number);
+
+        for bid in self.storage.get_bids_from(view_number).await? {
+            if self.builder_banned(bid.account()).await? {
+                continue;
+            }
+            // Bids past the lookahead are left out, as they would have been on submission
+            let _ = self.solver.bid_txs.insert(bid, &self.auction_options);
+        }
+
+        Ok(())
+    }
+
+    /// Rejects every bid submitted from now on with [`SolverError::ShuttingDown`].
+    pub fn stop_accepting_bids(&self) {
+        self.accepting_bids.store(false, Ordering::Relaxed);
+    }
+
+    /// Finalizes the auction of the latest view seen from the events service, if it is still
+    /// open, so that its results are persisted before the solver stops.
+    pub async fn flush_auction(&self) -> SolverResult<Option<AuctionOutcome>> {
+        let Some(view_number) = self.events_health.latest_view().map(ViewNumber::new) else {
+            return Ok(None);
+        };
+
+        if self.solver.finalized_view() >= Some(view_number) {
+            return Ok(None);
+        }
+
+        self.finalize_auction(view_number).await.map(Some)
+    }
+
+    pub async fn close_storage(&self) {
+        self.storage.close().await
     }
 }
 
 impl GlobalState {
-    pub fn new(db: PostgresClient, state: SolverState) -> anyhow::Result<Self> {
+    pub fn new(
+        storage: Arc<dyn SolverStorage>,
+        state: SolverState,
+        auction_options: AuctionOptions,
+        solver_key: SolverKey,
+    ) -> anyhow::Result<Self> {
         Ok(Self {
             solver: state,
-            database: db,
+            storage,
+            registrations: Cached::new(),
+            banned_builders: Cached::new(),
+            builder_registrations: Cached::new(),
+            bidding_paused: Cached::new(),
+            registration_writes: Mutex::new(()),
+            builder_registration_writes: Mutex::new(()),
+            finalization: Mutex::new(()),
+            admin_requests: Mutex::new(()),
+            auction_options,
+            solver_key,
+            metrics: SolverMetrics::new(),
+            events_health: Default::default(),
+            accepting_bids: AtomicBool::new(true),
+            finalized_auctions: Feed::new(),
+            registration_changes: Feed::new(),
         })
     }
+
+    async fn compute_auction_outcome(
+        &self,
+        view_number: ViewNumber,
+        bids: Vec<BidTx>,
+    ) -> SolverResult<AuctionOutcome> {
+        let registrations = self.registrations().await?;
+
+        let inputs = AuctionInputs::new(view_number, bids, registrations.values().cloned());
+
+        let timer = self.metrics.auction_computation_seconds.start_timer();
+        let outcome = compute_auction_results(inputs, &self.auction_options);
+        timer.observe_duration();
+
+        Ok(outcome)
+    }
+
+    /// Checks that a bid is well-formed and can take part in the auction for its view.
+    async fn validate_bid(&self, bid: &BidTx) -> SolverResult<()> {
+        if !self.accepting_bids.load(Ordering::Relaxed) {
+            return Err(SolverError::ShuttingDown);
+        }
+        if *self
+            .bidding_paused
+            .get(self.storage.bidding_paused())
+            .await?
+        {
+            return Err(SolverError::BiddingPaused);
+        }
+
+        bid.verify()
+            .map_err(|err| SolverError::InvalidBidSignature(err.to_string()))?;
+
+        self.check_builder_access(bid.account()).await?;
+        if self.auction_options.require_builder_registration {
+            self.get_builder_registration(bid.account()).await?;
+        }
+
+        // Bids are accepted for views whose auction is still open, up to a limit. The bid book
+        // checks again when the bid is added, in case the auction is closed in between.
+        let view = bid.view().u64();
+        let (first, last) = self.solver.bid_txs.open_views(&self.auction_options);
+        if !(first..=last).contains(&view) {
+            return Err(SolverError::BidViewOutOfRange { view, first, last });
+        }
+
+        let amount = bid.amount();
+        if amount == FeeAmount::from(0_u64) {
+            return Err(SolverError::ZeroBidAmount);
+        }
+        if let Some(max) = self.auction_options.max_bid_amount {
+            if amount > max {
+                return Err(SolverError::BidAmountTooLarge {
+                    amount: amount.to_string(),
+                    max: max.to_string(),
+                });
+            }
+        }
+
+        check_builder_url(&bid.url())?;
+
+        let namespaces = bid.namespaces();
+        if namespaces.is_empty() {
+            return Err(SolverError::EmptyBidNamespaces);
+        }
+
+        let registrations = self.registrations().await?;
+
+        let mut seen = HashSet::new();
+        for namespace_id in namespaces.iter().copied() {
+            if !seen.insert(namespace_id) {
+                return Err(SolverError::DuplicateBidNamespace(namespace_id));
+            }
+
+            match registrations.get(&namespace_id) {
+                None => return Err(SolverError::UnregisteredNamespace(namespace_id)),
+                Some(r) if !r.body.active => {
+                    return Err(SolverError::InactiveNamespace(namespace_id))
+                }
+                Some(_) => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Applies the operator's allow and deny lists and bans to the builder `account`.
+    ///
+    /// The lists are part of the deployment, like the operator keys: a permissioned deployment
+    /// fixes its builders up front. Builders excluded while the solver runs are banned through the
+    /// admin API instead, which persists the ban and records it in the audit log.
+    async fn check_builder_access(&self, account: FeeAccount) -> SolverResult<()> {
+        let allow_list = &self.auction_options.builder_allow_list;
+        let denied = self.auction_options.builder_deny_list.contains(&account);
+
+        if denied
+            || !(allow_list.is_empty() || allow_list.contains(&account))
+            || self.builder_banned(account).await?
+        {
+            return Err(SolverError::BuilderNotAllowed(account));
+        }
+
+        Ok(())
+    }
+
+    /// Registrations of every rollup, by namespace.
+    async fn registrations(&self) -> SolverResult<Arc<BTreeMap<NamespaceId, RollupRegistration>>> {
+        self.registrations
+            .get(async {
+                let registrations = self.storage.get_all_rollup_registrations().await?;
+                Ok(registrations
+                    .into_iter()
+                    .map(|r| (r.body.namespace_id, r))
+                    .collect())
+            })
+            .await
+    }
+
+    /// Writes a change to a rollup registration together with its entry in the change log, and
+    /// publishes it to subscribers.
+    ///
+    /// The caller must hold `registration_writes`.
+    async fn record_registration_change(
+        &self,
+        namespace_id: NamespaceId,
+        kind: RegistrationChangeKind,
+        registration: Option<RollupRegistration>,
+    ) -> SolverResult<()> {
+        let change = self
+            .storage
+            .write_registration_change(RegistrationChange {
+                // Assigned by the storage
+                cursor: 0,
+                namespace_id,
+                kind,
+                registration,
+                view_number: self.events_health.latest_view(),
+                timestamp: unix_time(),
+            })
+            .await?;
+
+        self.registrations
+            .update(|registrations| match &change.registration {
+                Some(registration) => {
+                    registrations.insert(namespace_id, registration.clone());
+                }
+                None => {
+                    registrations.remove(&namespace_id);
+                }
+            });
+        self.registration_changes.publish(change);
+
+        Ok(())
+    }
+
+    async fn builder_banned(&self, account: FeeAccount) -> SolverResult<bool> {
+        let banned = self
+            .banned_builders
+            .get(async {
+                let banned = self.storage.get_banned_builders().await?;
+                Ok(banned.into_iter().collect())
+            })
+            .await?;
+
+        Ok(banned.contains(&account))
+    }
+
+    async fn execute_admin_action(&self, action: &AdminAction) -> SolverResult<()> {
+        match action {
+            AdminAction::UpdateRollup(registration) => {
+                let _writes = self.registration_writes.lock().await;
+                self.record_registration_change(
+                    registration.body.namespace_id,
+                    RegistrationChangeKind::Updated,
+                    Some(registration.clone()),
+                )
+                .await?;
+            }
+            AdminAction::DeleteRollup(namespace_id) => {
+                let _writes = self.registration_writes.lock().await;
+                self.record_registration_change(
+                    *namespace_id,
+                    RegistrationChangeKind::Deleted,
+                    None,
+                )
+                .await?;
+            }
+            AdminAction::BanBuilder(account) => {
+                self.storage.ban_builder(*account).await?;
+                self.banned_builders.update(|banned| {
+                    banned.insert(*account);
+                });
+
+                // Bids the builder already submitted for open auctions are dropped as well
+                self.solver.bid_txs.remove_builder(account);
+            }
+            AdminAction::UnbanBuilder(account) => {
+                self.storage.unban_builder(*account).await?;
+                self.banned_builders.update(|banned| {
+                    banned.remove(account);
+                });
+            }
+            AdminAction::RerunAuction(view) => {
+                let view_number = ViewNumber::new(*view);
+                let previous = self.get_auction_outcome(view_number).await?;
+
+                let mut bids = Vec::new();
+                for bid in previous.inputs.bids {
+                    if !self.builder_banned(bid.account()).await? {
+                        bids.push(bid);
+                    }
+                }
+
+                let outcome = self.compute_auction_outcome(view_number, bids).await?;
+                self.storage.replace_auction_outcome(&outcome).await?;
+            }
+            AdminAction::PauseBidding | AdminAction::ResumeBidding => {
+                let paused = matches!(action, AdminAction::PauseBidding);
+                self.storage.set_bidding_paused(paused).await?;
+                self.bidding_paused
+                    .update(|bidding_paused| *bidding_paused = paused);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn finalized_auction_outcome(
+        &self,
+        view_number: ViewNumber,
+    ) -> SolverResult<Option<AuctionOutcome>> {
+        self.storage.get_auction_outcome(view_number).await
+    }
 }
 
 pub struct SolverState {
     pub stake_table: StakeTable,
-    // todo (abdul) : () will be replaced w BidTx
-    pub bid_txs: HashMap<ViewNumber, HashMap<BuilderKey, ()>>,
+    pub bid_txs: BidBook,
+    // Latest view whose auction has been finalized
+    finalized_view: RwLock<Option<ViewNumber>>,
+}
+
+impl SolverState {
+    pub fn new(stake_table: StakeTable) -> Self {
+        Self {
+            stake_table,
+            bid_txs: Default::default(),
+            finalized_view: RwLock::new(None),
+        }
+    }
+
+    pub fn finalized_view(&self) -> Option<ViewNumber> {
+        *self
+            .finalized_view
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn finalize_view(&self, view_number: ViewNumber) {
+        let mut finalized = self
+            .finalized_view
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        *finalized = (*finalized).max(Some(view_number));
+    }
 }
 
 pub struct StakeTable {
     pub known_nodes_with_stake: Vec<PeerConfig<PubKey>>,
 }
 
+/// A request of the leader of a view for the results of its auction, which may still be open.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResultsRequest {
+    pub view_number: u64,
+    pub leader: PubKey,
+    /// Signature of `leader` over the view number
+    pub signature: <PubKey as SignatureKey>::PureAssembledSignatureType,
+}
+
+impl ResultsRequest {
+    pub fn new(view_number: u64, private_key: &BLSPrivKey) -> SolverResult<Self> {
+        let signature = PubKey::sign(private_key, Self::commitment(view_number).as_ref())
+            .map_err(|err| SolverError::InvalidSignature(err.to_string()))?;
+
+        Ok(Self {
+            view_number,
+            leader: PubKey::from_private(private_key),
+            signature,
+        })
+    }
+
+    /// Checks that the request was signed by `leader`.
+    pub fn verify(&self) -> bool {
+        PubKey::validate(
+            &self.leader,
+            &self.signature,
+            Self::commitment(self.view_number).as_ref(),
+        )
+    }
+
+    fn commitment(view_number: u64) -> Commitment<Self> {
+        RawCommitmentBuilder::new("AUCTION_RESULTS_REQUEST")
+            .u64_field("view_number", view_number)
+            .finalize()
+    }
+}
+
+impl StakeTable {
+    /// The leader of `view_number`, taking turns in stake table order like HotShot's static
+    /// committee.
+    pub fn leader(&self, view_number: ViewNumber) -> Option<PubKey> {
+        let nodes = &self.known_nodes_with_stake;
+        if nodes.is_empty() {
+            return None;
+        }
+
+        let node = &nodes[(view_number.u64() % nodes.len() as u64) as usize];
+        Some(PubKey::public_key(&node.stake_table_entry))
+    }
+}
+
 #[async_trait]
 pub trait UpdateSolverState {
-    // TODO (abdul) : add BidTx from types crate.
-    async fn submit_bix_tx(&mut self) -> anyhow::Result<()>;
-    // TODO (abdul)
+    async fn submit_bid_tx(&self, bid: BidTx) -> SolverResult<()>;
     async fn register_rollup(
         &self,
         registration: RollupRegistration,
@@ -68,21 +502,85 @@ pub trait UpdateSolverState {
         update: RollupUpdate,
     ) -> SolverResult<RollupRegistration>;
     async fn get_all_rollup_registrations(&self) -> SolverResult<Vec<RollupRegistration>>;
-    // TODO (abdul) : return AuctionResults
+    /// Registers a builder, or replaces the metadata of a registered one.
+    async fn register_builder(
+        &self,
+        registration: BuilderRegistration,
+    ) -> SolverResult<BuilderRegistration>;
+    async fn get_builder_registration(
+        &self,
+        account: FeeAccount,
+    ) -> SolverResult<BuilderRegistration>;
+    async fn get_all_builder_registrations(&self) -> SolverResult<Vec<BuilderRegistration>>;
+    /// Returns the registrations of the builders that won the finalized auction for
+    /// `view_number`. Winners that never registered are skipped.
+    async fn get_auction_builders(
+        &self,
+        view_number: ViewNumber,
+    ) -> SolverResult<Vec<BuilderRegistration>>;
+    /// Closes the auction for `view_number`, persisting its results together with the inputs
+    /// they were computed from.
+    async fn finalize_auction(&self, view_number: ViewNumber) -> SolverResult<AuctionOutcome>;
+    /// Returns the persisted outcome of a finalized auction.
+    async fn get_auction_outcome(&self, view_number: ViewNumber) -> SolverResult<AuctionOutcome>;
     async fn calculate_auction_results_permissionless(
         &self,
         view_number: ViewNumber,
-    ) -> SolverResult<SolverAuctionResults>;
+    ) -> SolverResult<SignedAuctionResults>;
+    /// Returns the results of an auction to the leader of its view, before it is finalized.
     async fn calculate_auction_results_permissioned(
         &self,
-        view_number: ViewNumber,
-        _signauture: <SeqTypes as NodeType>::SignatureKey,
-    ) -> SolverResult<SolverAuctionResults>;
+        request: ResultsRequest,
+    ) -> SolverResult<SignedAuctionResults>;
+    /// Public key the auction results are signed with.
+    fn solver_key(&self) -> PubKey;
+    /// Streams the results of every auction finalized from now on.
+    fn subscribe_finalized_auctions(&self) -> BoxStream<'static, FinalizedAuction>;
+    /// Streams rollup registration changes. With a `cursor`, the changes recorded after it are
+    /// replayed first; without one, only changes made from now on are streamed.
+    async fn subscribe_registration_changes(
+        &self,
+        cursor: Option<u64>,
+    ) -> SolverResult<BoxStream<'static, RegistrationChange>>;
+}
+
+/// The state synchronizes concurrent updates itself, so tide-disco can share it without a lock.
+#[async_trait]
+impl ReadState for GlobalState {
+    type State = Self;
+
+    async fn read<T>(
+        &self,
+        op: impl Send + for<'a> FnOnce(&'a Self::State) -> BoxFuture<'a, T> + 'async_trait,
+    ) -> T {
+        op(self).await
+    }
 }
 
 #[async_trait]
 impl UpdateSolverState for GlobalState {
-    async fn submit_bix_tx(&mut self) -> anyhow::Result<()> {
+    async fn submit_bid_tx(&self, bid: BidTx) -> SolverResult<()> {
+        self.metrics.bids_received.inc();
+
+        let result = match self.validate_bid(&bid).await {
+            Ok(()) => self
+                .solver
+                .bid_txs
+                .insert(bid.clone(), &self.auction_options),
+            Err(err) => Err(err),
+        };
+        // Stored once in the book, so a bid that cannot be stored can be submitted again
+        let result = match result {
+            Ok(()) => self.storage.put_bid(&bid).await,
+            Err(err) => Err(err),
+        };
+        if let Err(err) = result {
+            self.metrics.bid_rejected(&err);
+            return Err(err);
+        }
+
+        self.metrics.bids_accepted.inc();
+
         Ok(())
     }
 
@@ -116,35 +614,13 @@ impl UpdateSolverState for GlobalState {
             return Err(SolverError::InvalidSignature(signature.to_string()));
         }
 
-        let db = self.database();
-
-        let exists: bool = sqlx::query_scalar(
-            "SELECT EXISTS(SELECT 1 FROM rollup_registrations where namespace_id = $1);",
+        let _writes = self.registration_writes.lock().await;
+        self.record_registration_change(
+            namespace_id,
+            RegistrationChangeKind::Registered,
+            Some(registration.clone()),
         )
-        .bind::<i64>(u64::from(namespace_id).try_into().map_err(overflow_err)?)
-        .fetch_one(db)
-        .await
-        .map_err(SolverError::from)?;
-
-        if exists {
-            return Err(SolverError::RollupAlreadyExists(namespace_id));
-        }
-
-        let json = serde_json::to_value(registration.clone()).map_err(serde_json_err)?;
-
-        let result = sqlx::query("INSERT INTO rollup_registrations VALUES ($1, $2);")
-            .bind::<i64>(u64::from(namespace_id).try_into().map_err(overflow_err)?)
-            .bind(&json)
-            .execute(db)
-            .await
-            .map_err(SolverError::from)?;
-
-        if result.rows_affected() != 1 {
-            return Err(SolverError::Database(format!(
-                "invalid num of rows affected. rows affected: {:?}",
-                result.rows_affected()
-            )));
-        }
+        .await?;
 
         Ok(registration)
     }
@@ -153,8 +629,6 @@ impl UpdateSolverState for GlobalState {
         &self,
         update: RollupUpdate,
     ) -> SolverResult<RollupRegistration> {
-        let db = self.database();
-
         let RollupUpdate { body, signature } = update;
 
         let commit = body.commit();
@@ -179,15 +653,13 @@ impl UpdateSolverState for GlobalState {
             return Err(SolverError::InvalidSignature(signature.to_string()));
         }
 
-        let result: RollupRegistrationResult =
-            sqlx::query_as("SELECT * from rollup_registrations where namespace_id = $1;")
-                .bind::<i64>(u64::from(namespace_id).try_into().map_err(overflow_err)?)
-                .fetch_one(db)
-                .await
-                .map_err(SolverError::from)?;
-
-        let mut registration =
-            serde_json::from_value::<RollupRegistration>(result.data).map_err(serde_json_err)?;
+        let _writes = self.registration_writes.lock().await;
+        let mut registration = self
+            .storage
+            .get_rollup_registration(namespace_id)
+            .await?
+            .ok_or(SolverError::RollupNotFound(namespace_id))?;
+        let was_active = registration.body.active;
 
         if let Some(reserve_url) = reserve_url {
             registration.body.reserve_url = reserve_url;
@@ -223,128 +695,366 @@ impl UpdateSolverState for GlobalState {
             registration.body.signature_keys = keys;
         }
 
-        let value = serde_json::to_value(&registration).map_err(serde_json_err)?;
+        let kind = if was_active && !registration.body.active {
+            RegistrationChangeKind::Deactivated
+        } else {
+            RegistrationChangeKind::Updated
+        };
+        self.record_registration_change(namespace_id, kind, Some(registration.clone()))
+            .await?;
 
-        let result =
-            sqlx::query("UPDATE rollup_registrations SET data = $1  WHERE namespace_id = $2;")
-                .bind(&value)
-                .bind::<i64>(u64::from(namespace_id).try_into().map_err(overflow_err)?)
-                .execute(db)
-                .await
-                .map_err(SolverError::from)?;
+        Ok(registration)
+    }
 
-        if result.rows_affected() != 1 {
-            return Err(SolverError::Database(format!(
-                "invalid num of rows affected. rows affected: {:?}",
-                result.rows_affected()
-            )));
+    async fn get_all_rollup_registrations(&self) -> SolverResult<Vec<RollupRegistration>> {
+        Ok(self.registrations().await?.values().cloned().collect())
+    }
+
+    async fn register_builder(
+        &self,
+        registration: BuilderRegistration,
+    ) -> SolverResult<BuilderRegistration> {
+        if !registration.verify() {
+            return Err(SolverError::InvalidSignature(
+                registration.signature.to_string(),
+            ));
         }
 
+        let body = &registration.body;
+        self.check_builder_access(body.account).await?;
+
+        check_builder_url(&body.url)?;
+
+        // The signature proves ownership of the account, so re-registering updates the metadata
+        let _writes = self.builder_registration_writes.lock().await;
+        self.storage.put_builder_registration(&registration).await?;
+        self.builder_registrations.update(|registrations| {
+            registrations.insert(body.account, registration.clone());
+        });
+
         Ok(registration)
     }
 
-    async fn get_all_rollup_registrations(&self) -> SolverResult<Vec<RollupRegistration>> {
-        let db = self.database();
+    async fn get_builder_registration(
+        &self,
+        account: FeeAccount,
+    ) -> SolverResult<BuilderRegistration> {
+        let registrations = self
+            .builder_registrations
+            .get(async {
+                let registrations = self.storage.get_all_builder_registrations().await?;
+                Ok(registrations
+                    .into_iter()
+                    .map(|registration| (registration.body.account, registration))
+                    .collect())
+            })
+            .await?;
 
-        let rows: Vec<RollupRegistrationResult> =
-            sqlx::query_as("SELECT * from rollup_registrations;")
-                .fetch_all(db)
-                .await
-                .map_err(SolverError::from)?;
+        registrations
+            .get(&account)
+            .cloned()
+            .ok_or(SolverError::UnregisteredBuilder(account))
+    }
 
-        rows.iter()
-            .map(|r| serde_json::from_value(r.data.clone()).map_err(serde_json_err))
-            .collect::<SolverResult<Vec<RollupRegistration>>>()
+    async fn get_all_builder_registrations(&self) -> SolverResult<Vec<BuilderRegistration>> {
+        self.storage.get_all_builder_registrations().await
     }
 
-    async fn calculate_auction_results_permissionless(
+    async fn get_auction_builders(
         &self,
         view_number: ViewNumber,
-    ) -> SolverResult<SolverAuctionResults> {
-        // todo (ab): actual logic needs to implemented
-        // for we, we just return some default results
+    ) -> SolverResult<Vec<BuilderRegistration>> {
+        let outcome = self.get_auction_outcome(view_number).await?;
 
-        let rollups = self.get_all_rollup_registrations().await?;
+        let mut builders = Vec::new();
+        for bid in outcome.results.winning_bids() {
+            match self.get_builder_registration(bid.account()).await {
+                Ok(registration) => builders.push(registration),
+                Err(SolverError::UnregisteredBuilder(_)) => {}
+                Err(err) => return Err(err),
+            }
+        }
 
-        let results = SolverAuctionResults::new(
-            view_number,
-            Vec::new(),
-            rollups
-                .into_iter()
-                .map(|r| (r.body.namespace_id, r.body.reserve_url))
-                .collect(),
-        );
+        Ok(builders)
+    }
+
+    async fn finalize_auction(&self, view_number: ViewNumber) -> SolverResult<AuctionOutcome> {
+        let _finalizing = self.finalization.lock().await;
+
+        if let Some(outcome) = self.finalized_auction_outcome(view_number).await? {
+            return Ok(outcome);
+        }
+
+        // Bids submitted from now on are rejected rather than left out of the auction
+        let bids = self.solver.bid_txs.close(view_number);
+        let outcome = self.compute_auction_outcome(view_number, bids).await?;
+
+        self.storage.insert_auction_outcome(&outcome).await?;
+
+        // Bids for finalized views can no longer win. Views before it that never finished, e.g.
+        // while the solver was disconnected from the events service, have no auction at all.
+        let skipped = self.solver.bid_txs.prune(view_number);
+        for (view, count) in skipped {
+            tracing::warn!("dropped {count} bids for view {view:?}, which had no auction");
+            self.metrics.bids_skipped.inc_by(count as u64);
+        }
+        self.solver.finalize_view(view_number);
+
+        // The outcome keeps the bids the auction was computed from
+        if let Err(err) = self.storage.delete_bids(view_number).await {
+            tracing::warn!("failed to delete stored bids up to view {view_number:?}: {err}");
+        }
 
-        Ok(results)
+        self.finalized_auctions.publish(FinalizedAuction {
+            view_number: view_number.u64(),
+            results: self.solver_key.sign(outcome.results.clone())?,
+            bid_count: Some(outcome.inputs.bids.len()),
+        });
+
+        Ok(outcome)
     }
-    async fn calculate_auction_results_permissioned(
+
+    async fn get_auction_outcome(&self, view_number: ViewNumber) -> SolverResult<AuctionOutcome> {
+        self.finalized_auction_outcome(view_number)
+            .await?
+            .ok_or(SolverError::AuctionNotFinalized(view_number.u64()))
+    }
+
+    async fn calculate_auction_results_permissionless(
         &self,
         view_number: ViewNumber,
-        _signauture: <SeqTypes as NodeType>::SignatureKey,
-    ) -> SolverResult<SolverAuctionResults> {
-        // todo (ab): actual logic needs to implemented
-        // for we, we just return some default results
+    ) -> SolverResult<SignedAuctionResults> {
+        let outcome = self.get_auction_outcome(view_number).await?;
+        self.solver_key.sign(outcome.results)
+    }
+    async fn calculate_auction_results_permissioned(
+        &self,
+        request: ResultsRequest,
+    ) -> SolverResult<SignedAuctionResults> {
+        let view_number = ViewNumber::new(request.view_number);
 
-        let rollups = self.get_all_rollup_registrations().await?;
+        // The current winning bids would let other builders outbid them by the smallest step
+        if self.solver.stake_table.leader(view_number) != Some(request.leader) {
+            return Err(SolverError::Unauthorized(format!(
+                "{} is not the leader of view {}",
+                request.leader, request.view_number
+            )));
+        }
+        if !request.verify() {
+            return Err(SolverError::Unauthorized("invalid signature".to_string()));
+        }
 
-        let results = SolverAuctionResults::new(
-            view_number,
-            Vec::new(),
-            rollups
-                .into_iter()
-                .map(|r| (r.body.namespace_id, r.body.reserve_url))
-                .collect(),
+        if let Some(outcome) = self.finalized_auction_outcome(view_number).await? {
+            return self.solver_key.sign(outcome.results);
+        }
+
+        // The leader gets the current results of an auction that is still open
+        let bids = self.solver.bid_txs.bids(view_number);
+        let outcome = self.compute_auction_outcome(view_number, bids).await?;
+        self.solver_key.sign_provisional(outcome.results)
+    }
+
+    fn solver_key(&self) -> PubKey {
+        self.solver_key.public_key()
+    }
+
+    fn subscribe_finalized_auctions(&self) -> BoxStream<'static, FinalizedAuction> {
+        self.finalized_auctions.subscribe()
+    }
+
+    async fn subscribe_registration_changes(
+        &self,
+        cursor: Option<u64>,
+    ) -> SolverResult<BoxStream<'static, RegistrationChange>> {
+        // Subscribe before reading the backlog so that no change falls in between
+        let live = self.registration_changes.subscribe();
+
+        let Some(cursor) = cursor else {
+            return Ok(live);
+        };
+
+        let backlog = self.storage.get_registration_changes(cursor).await?;
+
+        // Changes published while the backlog was read are in both
+        let last = backlog.last().map_or(cursor, |change| change.cursor);
+        let live = live.filter(move |change| future::ready(change.cursor > last));
+
+        Ok(stream::iter(backlog).chain(live).boxed())
+    }
+}
+
+#[async_trait]
+impl SolverStatus for GlobalState {
+    async fn export_metrics(&self) -> SolverResult<Registry> {
+        let registrations = self.get_all_rollup_registrations().await?;
+        let active = registrations.iter().filter(|r| r.body.active).count();
+
+        self.metrics
+            .registered_rollups
+            .set(registrations.len() as i64);
+        self.metrics.active_rollups.set(active as i64);
+
+        if let Some(stats) = self.storage.connection_stats() {
+            self.metrics
+                .database_connections
+                .set(stats.connections.into());
+            self.metrics
+                .database_idle_connections
+                .set(stats.idle as i64);
+        }
+
+        Ok(self.metrics.registry().clone())
+    }
+
+    async fn liveness(&self) -> Liveness {
+        Liveness {
+            event_handler_running: self.events_health.running(),
+        }
+    }
+
+    async fn readiness(&self) -> Readiness {
+        let database_reachable = timeout(DATABASE_PROBE_TIMEOUT, self.storage.ping())
+            .await
+            .map_or(false, |result| result.is_ok());
+
+        let latest_view = self.events_health.latest_view();
+        let finalized_view = self.solver.finalized_view().map(|view| view.u64());
+
+        Readiness {
+            database_reachable,
+            events_connected: self.events_health.connected(),
+            latest_view,
+            finalized_view,
+            views_behind: latest_view
+                .map(|latest| latest.saturating_sub(finalized_view.unwrap_or(0))),
+        }
+    }
+}
+
+#[async_trait]
+impl SolverAdmin for GlobalState {
+    async fn execute_admin_request(&self, request: AdminRequest) -> SolverResult<AuditLogEntry> {
+        let _executing = self.admin_requests.lock().await;
+        let request_commitment = request.body.commit().to_string();
+
+        if self
+            .storage
+            .admin_request_executed(&request_commitment)
+            .await?
+        {
+            return Err(SolverError::AdminRequestReplayed);
+        }
+
+        let result = self.execute_admin_action(&request.body.action).await;
+
+        let operator = request.operator.to_string();
+        let error = result.as_ref().err().map(ToString::to_string);
+        let executed_at: i64 = unix_time().try_into().map_err(overflow_err)?;
+
+        tracing::warn!(
+            "operator {operator} executed {:?}: {}",
+            request.body.action,
+            error.as_deref().unwrap_or("ok")
         );
 
-        Ok(results)
+        let entry = self
+            .storage
+            .append_audit_entry(AuditLogEntry {
+                // Assigned by the storage
+                id: 0,
+                operator,
+                request_commitment,
+                action: request.body.action,
+                error,
+                executed_at,
+            })
+            .await?;
+
+        result.map(|()| entry)
+    }
+
+    async fn get_audit_log(&self) -> SolverResult<Vec<AuditLogEntry>> {
+        self.storage.get_audit_log().await
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, FromRow)]
-struct RollupRegistrationResult {
-    namespace_id: i64,
-    data: Value,
+/// Checks that a builder URL can be reached, i.e. that it is an HTTP(S) URL with a host.
+fn check_builder_url(url: &Url) -> SolverResult<()> {
+    if !matches!(url.scheme(), "http" | "https") || url.host().is_none() {
+        return Err(SolverError::InvalidBuilderUrl(url.to_string()));
+    }
+
+    Ok(())
 }
 
-#[cfg(any(test, feature = "testing"))]
-impl GlobalState {
-    pub async fn mock() -> Self {
-        let db = hotshot_query_service::data_source::sql::testing::TmpDb::init().await;
-        let host = db.host();
-        let port = db.port();
-
-        let opts = crate::DatabaseOptions {
-            url: None,
-            host: Some(host),
-            port: Some(port),
-            db_name: None,
-            username: Some("postgres".to_string()),
-            password: Some("password".to_string()),
-            max_connections: Some(100),
-            acquire_timeout: None,
-            require_ssl: false,
-            migrations: true,
+/// An in-memory copy of data read on every bid, kept in sync with the storage by the writes
+/// made through [`GlobalState`].
+///
+/// Readers share a snapshot. Writers update the storage and then the copy; a copy loaded
+/// concurrently with a write is not kept, since it may predate the write.
+struct Cached<T> {
+    // Number of writes so far, and the copy once it was loaded
+    inner: RwLock<(u64, Option<Arc<T>>)>,
+}
+
+impl<T: Clone> Cached<T> {
+    fn new() -> Self {
+        Self {
+            inner: RwLock::new((0, None)),
+        }
+    }
+
+    async fn get(&self, load: impl Future<Output = SolverResult<T>>) -> SolverResult<Arc<T>> {
+        let writes = {
+            let inner = self.inner.read().unwrap_or_else(PoisonError::into_inner);
+            if let Some(value) = &inner.1 {
+                return Ok(value.clone());
+            }
+            inner.0
         };
 
-        let client = PostgresClient::connect(opts)
-            .await
-            .expect("failed to connect to database");
+        let value = Arc::new(load.await?);
 
-        Self {
-            solver: SolverState::mock(),
-            database: client,
+        let mut inner = self.inner.write().unwrap_or_else(PoisonError::into_inner);
+        if inner.0 == writes && inner.1.is_none() {
+            inner.1 = Some(value.clone());
+        }
+
+        Ok(value)
+    }
+
+    fn update(&self, f: impl FnOnce(&mut T)) {
+        let mut inner = self.inner.write().unwrap_or_else(PoisonError::into_inner);
+        inner.0 += 1;
+        if let Some(value) = &mut inner.1 {
+            f(Arc::make_mut(value));
         }
     }
 }
 
+#[cfg(all(any(test, feature = "testing"), not(target_os = "windows")))]
+impl GlobalState {
+    /// A solver state backed by a test database, which must be kept alive as long as the state.
+    pub async fn mock() -> (crate::database::mock::TestDatabase, Self) {
+        let (db, client) = crate::database::mock::setup_mock_database().await;
+
+        let state = Self::new(
+            Arc::new(client),
+            SolverState::mock(),
+            Default::default(),
+            SolverKey::generate(),
+        )
+        .expect("failed to create solver state");
+
+        (db, state)
+    }
+}
+
 #[cfg(any(test, feature = "testing"))]
 impl SolverState {
     pub fn mock() -> Self {
-        Self {
-            stake_table: StakeTable {
-                known_nodes_with_stake: crate::mock::generate_stake_table(),
-            },
-            bid_txs: Default::default(),
-        }
+        Self::new(StakeTable {
+            known_nodes_with_stake: crate::mock::generate_stake_table(),
+        })
     }
 }
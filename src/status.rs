@@ -0,0 +1,95 @@
+use std::borrow::Cow;
+
+use async_trait::async_trait;
+use futures::FutureExt;
+use prometheus::Registry;
+use serde::{Deserialize, Serialize};
+use tide_disco::{api::ApiError, method::ReadState, Api, StatusCode};
+use vbs::version::StaticVersionType;
+
+use crate::{load_api, SolverError, SolverResult};
+
+#[async_trait]
+pub trait SolverStatus {
+    /// Refreshes the sampled metrics and returns the registry to export.
+    async fn export_metrics(&self) -> SolverResult<Registry>;
+    /// Reports whether the solver's background tasks are still running.
+    async fn liveness(&self) -> Liveness;
+    /// Reports whether the solver's dependencies are reachable.
+    async fn readiness(&self) -> Readiness;
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Liveness {
+    pub event_handler_running: bool,
+}
+
+impl Liveness {
+    pub fn is_live(&self) -> bool {
+        self.event_handler_running
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Readiness {
+    pub database_reachable: bool,
+    pub events_connected: bool,
+    /// Highest view seen from the events service
+    pub latest_view: Option<u64>,
+    /// Latest view whose auction has been finalized
+    pub finalized_view: Option<u64>,
+    /// How many views the finalized auctions trail the latest view
+    pub views_behind: Option<u64>,
+}
+
+impl Readiness {
+    pub fn is_ready(&self) -> bool {
+        self.database_reachable && self.events_connected
+    }
+}
+
+fn unavailable(report: &impl Serialize) -> SolverError {
+    SolverError::Custom {
+        status: StatusCode::SERVICE_UNAVAILABLE,
+        message: serde_json::to_string(report).unwrap_or_default(),
+    }
+}
+
+pub fn define_status_api<State, VERSION>() -> Result<Api<State, SolverError, VERSION>, ApiError>
+where
+    VERSION: StaticVersionType + 'static,
+    State: 'static + Send + Sync + ReadState,
+    <State as ReadState>::State: Send + Sync + SolverStatus,
+{
+    let mut api = load_api::<State, SolverError, VERSION>(
+        None::<&str>,
+        include_str!("../api/status.toml"),
+        Vec::new(),
+    )?;
+
+    api.metrics("metrics", |_req, state| {
+        async move { Ok(Cow::Owned(state.export_metrics().await?)) }.boxed()
+    })?
+    .get("healthz", |_req, state| {
+        async move {
+            let liveness = state.liveness().await;
+            if !liveness.is_live() {
+                return Err(unavailable(&liveness));
+            }
+            Ok(liveness)
+        }
+        .boxed()
+    })?
+    .get("readyz", |_req, state| {
+        async move {
+            let readiness = state.readiness().await;
+            if !readiness.is_ready() {
+                return Err(unavailable(&readiness));
+            }
+            Ok(readiness)
+        }
+        .boxed()
+    })?;
+
+    Ok(api)
+}
@@ -0,0 +1,776 @@
+//! Persistence of the solver's state.
+//!
+//! [`GlobalState`](crate::state::GlobalState) only talks to storage through [`SolverStorage`],
+//! which is implemented for Postgres by [`PostgresClient`](crate::database::PostgresClient) and
+//! in memory by [`MemoryStorage`], e.g. to embed the solver without a separate database.
+//!
+//! Bids accepted for open auctions are stored until their auction is finalized, so that a
+//! restarted solver picks up the auctions where it left them. From then on, the bids an auction
+//! was computed from are kept as the inputs of its [`AuctionOutcome`].
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard, PoisonError,
+    },
+    time::Duration,
+};
+
+use async_std::{sync::Barrier, task::sleep};
+use async_trait::async_trait;
+use espresso_types::{
+    v0_3::{BidTx, RollupRegistration},
+    FeeAccount, NamespaceId,
+};
+use hotshot_types::{data::ViewNumber, traits::node_implementation::ConsensusTime};
+
+use crate::{
+    admin::AuditLogEntry,
+    auction::AuctionOutcome,
+    builders::BuilderRegistration,
+    feeds::{RegistrationChange, RegistrationChangeKind},
+    SolverError, SolverResult,
+};
+
+/// Size of a connection pool, for the metrics.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConnectionStats {
+    pub connections: u32,
+    pub idle: usize,
+}
+
+#[async_trait]
+pub trait SolverStorage: Send + Sync {
+    /// Applies a change to a rollup registration and appends it to the change log, in one
+    /// transaction, so neither is ever stored without the other.
+    ///
+    /// A `Registered` change fails with [`SolverError::RollupAlreadyExists`] if the namespace is
+    /// already registered, a change without a registration with
+    /// [`SolverError::RollupNotFound`] if it is not; any other change replaces the registration.
+    /// Returns the change with the cursor the storage assigned to it, which increases with every
+    /// change.
+    async fn write_registration_change(
+        &self,
+        change: RegistrationChange,
+    ) -> SolverResult<RegistrationChange>;
+    async fn get_rollup_registration(
+        &self,
+        namespace_id: NamespaceId,
+    ) -> SolverResult<Option<RollupRegistration>>;
+    async fn get_all_rollup_registrations(&self) -> SolverResult<Vec<RollupRegistration>>;
+
+    /// The changes recorded after `cursor`, in order.
+    async fn get_registration_changes(&self, cursor: u64) -> SolverResult<Vec<RegistrationChange>>;
+
+    /// Stores a builder registration, replacing the one for the same account.
+    async fn put_builder_registration(
+        &self,
+        registration: &BuilderRegistration,
+    ) -> SolverResult<()>;
+    async fn get_builder_registration(
+        &self,
+        account: FeeAccount,
+    ) -> SolverResult<Option<BuilderRegistration>>;
+    async fn get_all_builder_registrations(&self) -> SolverResult<Vec<BuilderRegistration>>;
+    async fn ban_builder(&self, account: FeeAccount) -> SolverResult<()>;
+    async fn unban_builder(&self, account: FeeAccount) -> SolverResult<()>;
+    async fn builder_banned(&self, account: FeeAccount) -> SolverResult<bool>;
+    async fn get_banned_builders(&self) -> SolverResult<Vec<FeeAccount>>;
+
+    /// Stores a bid accepted for an open auction. Storing the same bid again has no effect.
+    async fn put_bid(&self, bid: &BidTx) -> SolverResult<()>;
+    /// The stored bids for `view_number`.
+    async fn get_bids(&self, view_number: ViewNumber) -> SolverResult<Vec<BidTx>>;
+    /// The stored bids for `view_number` and every view after it.
+    async fn get_bids_from(&self, view_number: ViewNumber) -> SolverResult<Vec<BidTx>>;
+    /// Deletes the stored bids for `view_number` and every view before it.
+    async fn delete_bids(&self, view_number: ViewNumber) -> SolverResult<()>;
+
+    /// Stores the outcome of a finalized auction, unless one is stored for its view already.
+    async fn insert_auction_outcome(&self, outcome: &AuctionOutcome) -> SolverResult<()>;
+    /// Replaces the stored outcome of a finalized auction.
+    async fn replace_auction_outcome(&self, outcome: &AuctionOutcome) -> SolverResult<()>;
+    async fn get_auction_outcome(
+        &self,
+        view_number: ViewNumber,
+    ) -> SolverResult<Option<AuctionOutcome>>;
+    /// Outcomes of every finalized auction, in view order.
+    async fn get_all_auction_outcomes(&self) -> SolverResult<Vec<AuctionOutcome>>;
+
+    /// Whether an admin request with this commitment was executed before.
+    async fn admin_request_executed(&self, request_commitment: &str) -> SolverResult<bool>;
+    /// Appends an entry to the audit log. The `id` of `entry` is assigned by the storage.
+    async fn append_audit_entry(&self, entry: AuditLogEntry) -> SolverResult<AuditLogEntry>;
+    /// The audit log, oldest entry first.
+    async fn get_audit_log(&self) -> SolverResult<Vec<AuditLogEntry>>;
+    async fn set_bidding_paused(&self, paused: bool) -> SolverResult<()>;
+    async fn bidding_paused(&self) -> SolverResult<bool>;
+
+    /// Checks that the storage can serve requests.
+    async fn ping(&self) -> SolverResult<()>;
+    /// Stops serving requests, waiting for the ones in flight.
+    async fn close(&self);
+    fn is_closed(&self) -> bool;
+    /// Usage of the connection pool, if the storage has one.
+    fn connection_stats(&self) -> Option<ConnectionStats> {
+        None
+    }
+}
+
+/// Storage that keeps everything in memory and loses it when dropped.
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    tables: Mutex<MemoryTables>,
+    closed: AtomicBool,
+    latency: Duration,
+    // Barrier the next operation waits at, see `pause_next_operation`
+    pause: Mutex<Option<Arc<Barrier>>>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryTables {
+    rollup_registrations: BTreeMap<NamespaceId, RollupRegistration>,
+    registration_changes: Vec<RegistrationChange>,
+    builder_registrations: HashMap<FeeAccount, BuilderRegistration>,
+    builder_bans: HashSet<FeeAccount>,
+    bids: BTreeMap<u64, Vec<BidTx>>,
+    auction_outcomes: BTreeMap<u64, AuctionOutcome>,
+    audit_log: Vec<AuditLogEntry>,
+    bidding_paused: bool,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Storage whose every operation takes at least `latency`, like a remote database would.
+    pub fn with_latency(latency: Duration) -> Self {
+        Self {
+            latency,
+            ..Self::default()
+        }
+    }
+
+    /// Makes the next operation wait at `barrier` twice before it touches the tables: once when
+    /// it starts, so the other party knows it is in progress, and once more until it is released.
+    #[cfg(any(test, feature = "testing"))]
+    pub fn pause_next_operation(&self, barrier: Arc<Barrier>) {
+        *self.pause.lock().unwrap_or_else(PoisonError::into_inner) = Some(barrier);
+    }
+
+    async fn tables(&self) -> SolverResult<MutexGuard<'_, MemoryTables>> {
+        if !self.latency.is_zero() {
+            sleep(self.latency).await;
+        }
+        let pause = self
+            .pause
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        if let Some(barrier) = pause {
+            barrier.wait().await;
+            barrier.wait().await;
+        }
+        if self.is_closed() {
+            return Err(SolverError::Database("storage is closed".to_string()));
+        }
+
+        self.tables
+            .lock()
+            .map_err(|_| SolverError::Database("storage lock poisoned".to_string()))
+    }
+}
+
+#[async_trait]
+impl SolverStorage for MemoryStorage {
+    async fn write_registration_change(
+        &self,
+        mut change: RegistrationChange,
+    ) -> SolverResult<RegistrationChange> {
+        let namespace_id = change.namespace_id;
+        let mut tables = self.tables().await?;
+
+        match &change.registration {
+            Some(registration) => {
+                if change.kind == RegistrationChangeKind::Registered
+                    && tables.rollup_registrations.contains_key(&namespace_id)
+                {
+                    return Err(SolverError::RollupAlreadyExists(namespace_id));
+                }
+                tables
+                    .rollup_registrations
+                    .insert(namespace_id, registration.clone());
+            }
+            None => {
+                if tables.rollup_registrations.remove(&namespace_id).is_none() {
+                    return Err(SolverError::RollupNotFound(namespace_id));
+                }
+            }
+        }
+
+        // Cursors start at 1, like the Postgres sequence
+        change.cursor = tables.registration_changes.len() as u64 + 1;
+        tables.registration_changes.push(change.clone());
+
+        Ok(change)
+    }
+
+    async fn get_rollup_registration(
+        &self,
+        namespace_id: NamespaceId,
+    ) -> SolverResult<Option<RollupRegistration>> {
+        Ok(self
+            .tables()
+            .await?
+            .rollup_registrations
+            .get(&namespace_id)
+            .cloned())
+    }
+
+    async fn get_all_rollup_registrations(&self) -> SolverResult<Vec<RollupRegistration>> {
+        Ok(self
+            .tables()
+            .await?
+            .rollup_registrations
+            .values()
+            .cloned()
+            .collect())
+    }
+
+    async fn get_registration_changes(&self, cursor: u64) -> SolverResult<Vec<RegistrationChange>> {
+        Ok(self
+            .tables()
+            .await?
+            .registration_changes
+            .iter()
+            .filter(|change| change.cursor > cursor)
+            .cloned()
+            .collect())
+    }
+
+    async fn put_builder_registration(
+        &self,
+        registration: &BuilderRegistration,
+    ) -> SolverResult<()> {
+        self.tables()
+            .await?
+            .builder_registrations
+            .insert(registration.body.account, registration.clone());
+
+        Ok(())
+    }
+
+    async fn get_builder_registration(
+        &self,
+        account: FeeAccount,
+    ) -> SolverResult<Option<BuilderRegistration>> {
+        Ok(self
+            .tables()
+            .await?
+            .builder_registrations
+            .get(&account)
+            .cloned())
+    }
+
+    async fn get_all_builder_registrations(&self) -> SolverResult<Vec<BuilderRegistration>> {
+        Ok(self
+            .tables()
+            .await?
+            .builder_registrations
+            .values()
+            .cloned()
+            .collect())
+    }
+
+    async fn ban_builder(&self, account: FeeAccount) -> SolverResult<()> {
+        self.tables().await?.builder_bans.insert(account);
+        Ok(())
+    }
+
+    async fn unban_builder(&self, account: FeeAccount) -> SolverResult<()> {
+        self.tables().await?.builder_bans.remove(&account);
+        Ok(())
+    }
+
+    async fn builder_banned(&self, account: FeeAccount) -> SolverResult<bool> {
+        Ok(self.tables().await?.builder_bans.contains(&account))
+    }
+
+    async fn get_banned_builders(&self) -> SolverResult<Vec<FeeAccount>> {
+        Ok(self.tables().await?.builder_bans.iter().copied().collect())
+    }
+
+    async fn put_bid(&self, bid: &BidTx) -> SolverResult<()> {
+        let mut tables = self.tables().await?;
+        let bids = tables.bids.entry(bid.view().u64()).or_default();
+        if !bids.contains(bid) {
+            bids.push(bid.clone());
+        }
+
+        Ok(())
+    }
+
+    async fn get_bids(&self, view_number: ViewNumber) -> SolverResult<Vec<BidTx>> {
+        Ok(self
+            .tables()
+            .await?
+            .bids
+            .get(&view_number.u64())
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn get_bids_from(&self, view_number: ViewNumber) -> SolverResult<Vec<BidTx>> {
+        Ok(self
+            .tables()
+            .await?
+            .bids
+            .range(view_number.u64()..)
+            .flat_map(|(_, bids)| bids.iter().cloned())
+            .collect())
+    }
+
+    async fn delete_bids(&self, view_number: ViewNumber) -> SolverResult<()> {
+        self.tables()
+            .await?
+            .bids
+            .retain(|view, _| *view > view_number.u64());
+
+        Ok(())
+    }
+
+    async fn insert_auction_outcome(&self, outcome: &AuctionOutcome) -> SolverResult<()> {
+        self.tables()
+            .await?
+            .auction_outcomes
+            .entry(outcome.inputs.view_number.u64())
+            .or_insert_with(|| outcome.clone());
+
+        Ok(())
+    }
+
+    async fn replace_auction_outcome(&self, outcome: &AuctionOutcome) -> SolverResult<()> {
+        if let Some(stored) = self
+            .tables()
+            .await?
+            .auction_outcomes
+            .get_mut(&outcome.inputs.view_number.u64())
+        {
+            *stored = outcome.clone();
+        }
+
+        Ok(())
+    }
+
+    async fn get_auction_outcome(
+        &self,
+        view_number: ViewNumber,
+    ) -> SolverResult<Option<AuctionOutcome>> {
+        Ok(self
+            .tables()
+            .await?
+            .auction_outcomes
+            .get(&view_number.u64())
+            .cloned())
+    }
+
+    async fn get_all_auction_outcomes(&self) -> SolverResult<Vec<AuctionOutcome>> {
+        Ok(self
+            .tables()
+            .await?
+            .auction_outcomes
+            .values()
+            .cloned()
+            .collect())
+    }
+
+    async fn admin_request_executed(&self, request_commitment: &str) -> SolverResult<bool> {
+        Ok(self
+            .tables()
+            .await?
+            .audit_log
+            .iter()
+            .any(|entry| entry.request_commitment == request_commitment))
+    }
+
+    async fn append_audit_entry(&self, mut entry: AuditLogEntry) -> SolverResult<AuditLogEntry> {
+        let mut tables = self.tables().await?;
+
+        entry.id = tables.audit_log.len() as i64 + 1;
+        tables.audit_log.push(entry.clone());
+
+        Ok(entry)
+    }
+
+    async fn get_audit_log(&self) -> SolverResult<Vec<AuditLogEntry>> {
+        Ok(self.tables().await?.audit_log.clone())
+    }
+
+    async fn set_bidding_paused(&self, paused: bool) -> SolverResult<()> {
+        self.tables().await?.bidding_paused = paused;
+        Ok(())
+    }
+
+    async fn bidding_paused(&self) -> SolverResult<bool> {
+        Ok(self.tables().await?.bidding_paused)
+    }
+
+    async fn ping(&self) -> SolverResult<()> {
+        self.tables().await.map(|_| ())
+    }
+
+    async fn close(&self) {
+        self.closed.store(true, Ordering::Relaxed);
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(all(any(test, feature = "testing"), not(target_os = "windows")))]
+pub mod mock {
+    use std::{env, sync::Arc};
+
+    use super::{MemoryStorage, SolverStorage};
+    use crate::database::mock::{setup_mock_database, TestDatabase};
+
+    /// Selects the storage backend tests run against: `postgres` (the default) or `memory`
+    pub const TEST_STORAGE_ENV: &str = "MARKETPLACE_SOLVER_TEST_STORAGE";
+
+    /// Storage for a test. The test database, if any, must be kept alive as long as the storage.
+    pub async fn setup_mock_storage() -> (Option<TestDatabase>, Arc<dyn SolverStorage>) {
+        match env::var(TEST_STORAGE_ENV).ok().as_deref() {
+            None | Some("postgres") => {
+                let (db, client) = setup_mock_database().await;
+                (Some(db), Arc::new(client))
+            }
+            Some("memory") => (None, Arc::new(MemoryStorage::new())),
+            Some(other) => panic!("invalid {TEST_STORAGE_ENV}: {other}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use espresso_types::{v0_3::RollupRegistration, EthKeyPair};
+    use hotshot_types::{data::ViewNumber, traits::node_implementation::ConsensusTime};
+    use tide_disco::Url;
+
+    use super::{MemoryStorage, SolverStorage};
+    use crate::{
+        admin::{AdminAction, AuditLogEntry},
+        auction::{compute_auction_results, AuctionInputs},
+        builders::BuilderRegistrationBody,
+        feeds::{RegistrationChange, RegistrationChangeKind},
+        testing::{bid, registration},
+        AuctionOptions, SolverError,
+    };
+
+    fn change(
+        namespace_id: u64,
+        kind: RegistrationChangeKind,
+        registration: Option<&RollupRegistration>,
+    ) -> RegistrationChange {
+        RegistrationChange {
+            cursor: 0,
+            namespace_id: namespace_id.into(),
+            kind,
+            registration: registration.cloned(),
+            view_number: Some(5),
+            timestamp: 1000,
+        }
+    }
+
+    async fn check_rollup_registrations(storage: &dyn SolverStorage) {
+        let first = registration(1, 100, true);
+        storage
+            .write_registration_change(change(1, RegistrationChangeKind::Registered, Some(&first)))
+            .await
+            .unwrap();
+
+        match storage
+            .write_registration_change(change(1, RegistrationChangeKind::Registered, Some(&first)))
+            .await
+        {
+            Err(SolverError::RollupAlreadyExists(namespace_id)) => {
+                assert_eq!(namespace_id, 1_u64.into())
+            }
+            result => panic!("result {result:?}"),
+        }
+
+        let mut updated = first.clone();
+        updated.body.reserve_price = 200.into();
+        storage
+            .write_registration_change(change(1, RegistrationChangeKind::Updated, Some(&updated)))
+            .await
+            .unwrap();
+        storage
+            .write_registration_change(change(
+                2,
+                RegistrationChangeKind::Registered,
+                Some(&registration(2, 100, true)),
+            ))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            storage.get_rollup_registration(1_u64.into()).await.unwrap(),
+            Some(updated)
+        );
+        assert_eq!(
+            storage.get_all_rollup_registrations().await.unwrap().len(),
+            2
+        );
+
+        storage
+            .write_registration_change(change(1, RegistrationChangeKind::Deleted, None))
+            .await
+            .unwrap();
+        match storage
+            .write_registration_change(change(1, RegistrationChangeKind::Deleted, None))
+            .await
+        {
+            Err(SolverError::RollupNotFound(namespace_id)) => {
+                assert_eq!(namespace_id, 1_u64.into())
+            }
+            result => panic!("result {result:?}"),
+        }
+        assert_eq!(
+            storage.get_rollup_registration(1_u64.into()).await.unwrap(),
+            None
+        );
+    }
+
+    async fn check_registration_changes(storage: &dyn SolverStorage) {
+        // Earlier checks may have recorded changes already
+        let start = storage
+            .get_registration_changes(0)
+            .await
+            .unwrap()
+            .last()
+            .map_or(0, |change| change.cursor);
+        let first = registration(3, 100, true);
+
+        let registered = storage
+            .write_registration_change(change(3, RegistrationChangeKind::Registered, Some(&first)))
+            .await
+            .unwrap();
+
+        // A rejected write records no change
+        storage
+            .write_registration_change(change(3, RegistrationChangeKind::Registered, Some(&first)))
+            .await
+            .unwrap_err();
+        storage
+            .write_registration_change(change(4, RegistrationChangeKind::Deleted, None))
+            .await
+            .unwrap_err();
+
+        let mut deletion = change(3, RegistrationChangeKind::Deleted, None);
+        deletion.timestamp = 1001;
+        let deleted = storage.write_registration_change(deletion).await.unwrap();
+        assert!(registered.cursor < deleted.cursor);
+
+        let changes = storage.get_registration_changes(start).await.unwrap();
+        assert_eq!(changes, [registered.clone(), deleted]);
+        assert_eq!(changes[0].registration, Some(first));
+        assert_eq!(changes[0].view_number, Some(5));
+        assert_eq!(changes[1].kind, RegistrationChangeKind::Deleted);
+        assert_eq!(changes[1].timestamp, 1001);
+
+        let after = storage
+            .get_registration_changes(registered.cursor)
+            .await
+            .unwrap();
+        assert_eq!(after, changes[1..]);
+    }
+
+    async fn check_builders(storage: &dyn SolverStorage) {
+        let key = EthKeyPair::random();
+        let account = key.fee_account();
+        let registration = BuilderRegistrationBody {
+            account,
+            url: Url::from_str("http://builder").unwrap(),
+            text: "builder".to_string(),
+        }
+        .signed(&key)
+        .unwrap();
+
+        assert_eq!(
+            storage.get_builder_registration(account).await.unwrap(),
+            None
+        );
+        storage
+            .put_builder_registration(&registration)
+            .await
+            .unwrap();
+        assert_eq!(
+            storage.get_builder_registration(account).await.unwrap(),
+            Some(registration.clone())
+        );
+        assert_eq!(
+            storage.get_all_builder_registrations().await.unwrap(),
+            vec![registration]
+        );
+
+        assert!(!storage.builder_banned(account).await.unwrap());
+        storage.ban_builder(account).await.unwrap();
+        storage.ban_builder(account).await.unwrap();
+        assert!(storage.builder_banned(account).await.unwrap());
+        assert_eq!(storage.get_banned_builders().await.unwrap(), vec![account]);
+        storage.unban_builder(account).await.unwrap();
+        assert!(!storage.builder_banned(account).await.unwrap());
+        assert!(storage.get_banned_builders().await.unwrap().is_empty());
+    }
+
+    async fn check_auction_outcomes(storage: &dyn SolverStorage) {
+        let registrations = vec![registration(1, 100, true)];
+        let outcome = |view: u64, amount: u64| {
+            let inputs = AuctionInputs::new(
+                ViewNumber::new(view),
+                vec![bid(view, &[1], amount)],
+                registrations.clone(),
+            );
+            compute_auction_results(inputs, &AuctionOptions::default())
+        };
+
+        let first = outcome(2, 300);
+        storage.insert_auction_outcome(&first).await.unwrap();
+        storage
+            .insert_auction_outcome(&outcome(1, 300))
+            .await
+            .unwrap();
+
+        // A finalized auction is never overwritten by inserting
+        storage
+            .insert_auction_outcome(&outcome(2, 500))
+            .await
+            .unwrap();
+        assert_eq!(
+            storage
+                .get_auction_outcome(ViewNumber::new(2))
+                .await
+                .unwrap(),
+            Some(first)
+        );
+
+        let rerun = outcome(2, 400);
+        storage.replace_auction_outcome(&rerun).await.unwrap();
+        assert_eq!(
+            storage
+                .get_auction_outcome(ViewNumber::new(2))
+                .await
+                .unwrap(),
+            Some(rerun)
+        );
+        assert_eq!(
+            storage
+                .get_auction_outcome(ViewNumber::new(3))
+                .await
+                .unwrap(),
+            None
+        );
+
+        let views: Vec<ViewNumber> = storage
+            .get_all_auction_outcomes()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|outcome| outcome.inputs.view_number)
+            .collect();
+        assert_eq!(views, vec![ViewNumber::new(1), ViewNumber::new(2)]);
+    }
+
+    async fn check_bids(storage: &dyn SolverStorage) {
+        let bids = [bid(1, &[1], 100), bid(2, &[1], 200), bid(3, &[1], 300)];
+        for bid in &bids {
+            storage.put_bid(bid).await.unwrap();
+        }
+        // Storing a bid again has no effect
+        storage.put_bid(&bids[1]).await.unwrap();
+
+        assert_eq!(
+            storage.get_bids(ViewNumber::new(2)).await.unwrap(),
+            vec![bids[1].clone()]
+        );
+        assert_eq!(
+            storage.get_bids_from(ViewNumber::new(2)).await.unwrap(),
+            bids[1..].to_vec()
+        );
+
+        storage.delete_bids(ViewNumber::new(2)).await.unwrap();
+        assert!(storage
+            .get_bids(ViewNumber::new(2))
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            storage.get_bids_from(ViewNumber::new(1)).await.unwrap(),
+            vec![bids[2].clone()]
+        );
+        storage.delete_bids(ViewNumber::new(3)).await.unwrap();
+    }
+
+    async fn check_audit_log(storage: &dyn SolverStorage) {
+        let entry = |commitment: &str| AuditLogEntry {
+            id: 0,
+            operator: "operator".to_string(),
+            request_commitment: commitment.to_string(),
+            action: AdminAction::PauseBidding,
+            error: None,
+            executed_at: 1000,
+        };
+
+        assert!(!storage.admin_request_executed("first").await.unwrap());
+        let first = storage.append_audit_entry(entry("first")).await.unwrap();
+        let second = storage.append_audit_entry(entry("second")).await.unwrap();
+        assert!(first.id < second.id);
+        assert!(storage.admin_request_executed("first").await.unwrap());
+
+        assert_eq!(storage.get_audit_log().await.unwrap(), vec![first, second]);
+    }
+
+    async fn check_bidding_paused(storage: &dyn SolverStorage) {
+        assert!(!storage.bidding_paused().await.unwrap());
+        storage.set_bidding_paused(true).await.unwrap();
+        storage.set_bidding_paused(true).await.unwrap();
+        assert!(storage.bidding_paused().await.unwrap());
+        storage.set_bidding_paused(false).await.unwrap();
+        assert!(!storage.bidding_paused().await.unwrap());
+    }
+
+    async fn check_storage(storage: &dyn SolverStorage) {
+        check_rollup_registrations(storage).await;
+        check_registration_changes(storage).await;
+        check_builders(storage).await;
+        check_auction_outcomes(storage).await;
+        check_bids(storage).await;
+        check_audit_log(storage).await;
+        check_bidding_paused(storage).await;
+
+        storage.ping().await.unwrap();
+        storage.close().await;
+        assert!(storage.is_closed());
+    }
+
+    #[async_std::test]
+    async fn test_memory_storage() {
+        let storage = MemoryStorage::new();
+        check_storage(&storage).await;
+
+        // A closed storage rejects requests
+        storage.ping().await.unwrap_err();
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[async_std::test]
+    async fn test_postgres_storage() {
+        let (db, client) = crate::database::mock::setup_mock_database().await;
+        check_storage(&client).await;
+        drop(db);
+    }
+}
@@ -1,135 +1,321 @@
-#![cfg(all(any(test, feature = "testing"), not(target_os = "windows")))]
+//! Test support: registration and bid fixtures, and a solver served against mock services.
+//!
+//! The mock solver stores its data in a test database, which is not available on Windows.
+#![cfg(any(test, feature = "testing"))]
 #![allow(dead_code)]
+use std::str::FromStr;
+#[cfg(not(target_os = "windows"))]
 use std::sync::Arc;
 
+#[cfg(not(target_os = "windows"))]
 use async_compatibility_layer::art::async_spawn;
-use async_std::{sync::RwLock, task::JoinHandle};
+#[cfg(not(target_os = "windows"))]
+use async_std::task::JoinHandle;
+#[cfg(not(target_os = "windows"))]
 use espresso_types::SeqTypes;
-use hotshot_query_service::data_source::sql::testing::TmpDb;
+use espresso_types::{
+    v0_3::{BidTx, BidTxBody, RollupRegistration, RollupRegistrationBody},
+    EthKeyPair, FeeAmount,
+};
+use hotshot::types::{BLSPubKey, SignatureKey};
+#[cfg(not(target_os = "windows"))]
 use hotshot_types::traits::node_implementation::NodeType;
+use hotshot_types::{
+    data::ViewNumber, signature_key::BLSPrivKey, traits::node_implementation::ConsensusTime,
+};
+#[cfg(not(target_os = "windows"))]
 use portpicker::pick_unused_port;
-use tide_disco::{App, Url};
+#[cfg(not(target_os = "windows"))]
+use tide_disco::App;
+use tide_disco::Url;
+#[cfg(not(target_os = "windows"))]
 use vbs::version::StaticVersionType;
 
+use crate::client::sign_registration;
+#[cfg(not(target_os = "windows"))]
 use crate::{
-    database::{mock::setup_mock_database, PostgresClient},
-    define_api, handle_events,
-    mock::run_mock_event_service,
+    admin::define_admin_api,
+    database::mock::TestDatabase,
+    define_api, define_status_api, handle_events_with_reconnect,
+    listener::{ListenerHandle, SolverListener},
+    mock::{run_mock_event_service_with, MockEvents, MockEventsHandle},
+    signing::SolverKey,
     state::{GlobalState, SolverState, StakeTable},
-    EventsServiceClient, SolverError,
+    storage::{mock::setup_mock_storage, SolverStorage},
+    AdminOptions, ApiOptions, AuctionOptions, EventsServiceClient, ServerOptions, SolverError,
 };
 
+#[cfg(not(target_os = "windows"))]
 pub struct MockSolver {
     pub events_api: Url,
+    /// Controls the mock events service the solver is subscribed to
+    pub events: MockEventsHandle,
     pub solver_api: Url,
-    pub state: Arc<RwLock<GlobalState>>,
-    pub database: PostgresClient,
+    pub status_api: Url,
+    pub admin_api: Url,
+    /// Key authorized to sign requests to the admin API
+    pub operator_key: BLSPrivKey,
+    pub state: Arc<GlobalState>,
+    pub storage: Arc<dyn SolverStorage>,
+    /// Task handling events from the mock events service, unless a test took it over
+    pub event_handler: Option<JoinHandle<()>>,
+    /// Stops and drains the listener of the solver's HTTP server
+    pub listener: ListenerHandle,
     pub handles: Vec<JoinHandle<()>>,
-    pub tmp_db: TmpDb,
+    /// Test database backing the storage, unless it is in memory
+    pub tmp_db: Option<TestDatabase>,
 }
 
+#[cfg(not(target_os = "windows"))]
 impl MockSolver {
     pub fn solver_api(&self) -> Url {
         self.solver_api.clone()
     }
 
-    pub fn state(&self) -> Arc<RwLock<GlobalState>> {
+    pub fn status_api(&self) -> Url {
+        self.status_api.clone()
+    }
+
+    pub fn admin_api(&self) -> Url {
+        self.admin_api.clone()
+    }
+
+    pub fn state(&self) -> Arc<GlobalState> {
         self.state.clone()
     }
 }
 
+#[cfg(not(target_os = "windows"))]
 impl Drop for MockSolver {
     fn drop(&mut self) {
         println!("canceling handles");
 
+        if let Some(handle) = self.event_handler.take() {
+            async_std::task::block_on(handle.cancel());
+        }
         while let Some(handle) = self.handles.pop() {
             async_std::task::block_on(handle.cancel());
         }
+        async_std::task::block_on(self.events.stop());
     }
 }
 
+#[cfg(not(target_os = "windows"))]
 impl MockSolver {
     pub async fn init() -> Self {
-        let (tmp_db, database) = setup_mock_database().await;
-        let (url, event_api_handle, generate_events_handle) = run_mock_event_service();
+        Self::init_with_events(MockEvents::Generated).await
+    }
+
+    /// Starts a solver subscribed to a mock events service that serves `events`.
+    pub async fn init_with_events(events: MockEvents) -> Self {
+        let (tmp_db, storage) = setup_mock_storage().await;
+        Self::init_with_storage(events, tmp_db, storage).await
+    }
+
+    /// Starts a solver backed by `storage`, and the test database behind it if there is one.
+    pub async fn init_with_storage(
+        events: MockEvents,
+        tmp_db: Option<TestDatabase>,
+        storage: Arc<dyn SolverStorage>,
+    ) -> Self {
+        Self::init_with_options(
+            events,
+            tmp_db,
+            storage,
+            Default::default(),
+            Default::default(),
+        )
+        .await
+    }
+
+    /// Starts a solver backed by `storage` that serves its API with `api_options` and runs
+    /// auctions with `auction_options`.
+    pub async fn init_with_options(
+        events: MockEvents,
+        tmp_db: Option<TestDatabase>,
+        storage: Arc<dyn SolverStorage>,
+        api_options: ApiOptions,
+        auction_options: AuctionOptions,
+    ) -> Self {
+        let (url, events) = run_mock_event_service_with(events);
 
         let client = EventsServiceClient::new(url.clone()).await;
         let startup_info = client.get_startup_info().await.unwrap();
-        let stream = client.get_event_stream().await.unwrap();
 
-        let solver_state = SolverState {
-            stake_table: StakeTable {
-                known_nodes_with_stake: startup_info.known_node_with_stake,
-            },
-            bid_txs: Default::default(),
-        };
+        let solver_state = SolverState::new(StakeTable {
+            known_nodes_with_stake: startup_info.known_node_with_stake,
+        });
 
-        let state = Arc::new(RwLock::new(
-            GlobalState::new(database.clone(), solver_state).unwrap(),
-        ));
+        let state = Arc::new(
+            GlobalState::new(
+                storage.clone(),
+                solver_state,
+                auction_options,
+                SolverKey::generate(),
+            )
+            .unwrap(),
+        );
 
-        let event_handler_handle = async_spawn({
-            let state = state.clone();
-            async move {
-                let _ = handle_events(stream, state).await;
-            }
-        });
+        let event_handler = async_spawn(handle_events_with_reconnect(url.clone(), state.clone()));
 
         let mut app = App::<_, SolverError>::with_state(state.clone());
         app.with_version(env!("CARGO_PKG_VERSION").parse().unwrap());
 
-        let mut api = define_api(Default::default()).unwrap();
+        let max_body_size = api_options.limits.max_body_size;
+        let mut api = define_api(api_options).unwrap();
         api.with_version(env!("CARGO_PKG_VERSION").parse().unwrap());
 
-        app.register_module::<SolverError, <SeqTypes as NodeType>::Base>("solver_api", api)
+        let server_options = ServerOptions {
+            host: "localhost".to_string(),
+            port: pick_unused_port().expect("no free port"),
+            ..Default::default()
+        };
+
+        app.register_module::<SolverError, <SeqTypes as NodeType>::Base>(
+            &server_options.api_prefix,
+            api,
+        )
+        .unwrap();
+
+        let mut status_api = define_status_api().unwrap();
+        status_api.with_version(env!("CARGO_PKG_VERSION").parse().unwrap());
+
+        app.register_module::<SolverError, <SeqTypes as NodeType>::Base>("status", status_api)
             .unwrap();
 
-        let solver_api_port = pick_unused_port().expect("no free port");
-        let solver_url: Url = Url::parse(&format!("http://localhost:{solver_api_port}")).unwrap();
+        let operator_key = BLSPrivKey::generate(&mut rand::thread_rng());
+        let mut admin_api = define_admin_api(AdminOptions {
+            operator_keys: vec![BLSPubKey::from_private(&operator_key)],
+        })
+        .unwrap();
+        admin_api.with_version(env!("CARGO_PKG_VERSION").parse().unwrap());
 
-        let solver_api_handle = async_spawn({
-            let solver_url = solver_url.clone();
-            async move {
-                let _ = app
-                    .serve(solver_url, <SeqTypes as NodeType>::Base::instance())
-                    .await;
-            }
+        app.register_module::<SolverError, <SeqTypes as NodeType>::Base>("admin", admin_api)
+            .unwrap();
+
+        let solver_url = server_options.bind_url().unwrap();
+
+        let listener =
+            SolverListener::new(&server_options.host, server_options.port, max_body_size)
+                .with_alias("/metrics", "/status/metrics");
+        let listener_handle = listener.handle();
+        let solver_api_handle = async_spawn(async move {
+            let _ = app
+                .serve(listener, <SeqTypes as NodeType>::Base::instance())
+                .await;
         });
 
-        let solver_api = solver_url.join("solver_api").unwrap();
+        let solver_api = server_options.solver_api_url(&solver_url).unwrap();
+        let status_api = solver_url.join("status").unwrap();
+        let admin_api = solver_url.join("admin").unwrap();
 
-        let handles = vec![
-            generate_events_handle,
-            event_handler_handle,
-            event_api_handle,
-            solver_api_handle,
-        ];
+        let handles = vec![solver_api_handle];
 
         MockSolver {
             events_api: url,
+            events,
             solver_api,
+            status_api,
+            admin_api,
+            operator_key,
             state,
-            database,
+            storage,
             tmp_db,
+            event_handler: Some(event_handler),
+            listener: listener_handle,
             handles,
         }
     }
 }
 
-#[cfg(test)]
+/// A registration of `namespace_id`, signed with a new key.
+pub fn registration(namespace_id: u64, reserve_price: u64, active: bool) -> RollupRegistration {
+    let private_key = BLSPrivKey::generate(&mut rand::thread_rng());
+    let signature_key = BLSPubKey::from_private(&private_key);
+
+    let body = RollupRegistrationBody {
+        namespace_id: namespace_id.into(),
+        reserve_url: Url::from_str(&format!("http://reserve-{namespace_id}")).unwrap(),
+        reserve_price: reserve_price.into(),
+        active,
+        signature_keys: vec![signature_key],
+        text: format!("rollup {namespace_id}"),
+        signature_key,
+    };
+
+    sign_registration(body, &private_key).expect("failed to sign registration")
+}
+
+/// A bid of a new builder for `namespaces` in `view`.
+pub fn bid(view: u64, namespaces: &[u64], amount: u64) -> BidTx {
+    signed_bid(&EthKeyPair::random(), view, namespaces, amount)
+}
+
+/// A bid of the builder with `key` for `namespaces` in `view`.
+pub fn signed_bid(key: &EthKeyPair, view: u64, namespaces: &[u64], amount: u64) -> BidTx {
+    BidTxBody::new(
+        key.fee_account(),
+        FeeAmount::from(amount),
+        ViewNumber::new(view),
+        namespaces.iter().map(|ns| (*ns).into()).collect(),
+        Url::from_str("http://builder").unwrap(),
+    )
+    .signed(key)
+    .expect("failed to sign bid")
+}
+
+#[cfg(all(test, not(target_os = "windows")))]
 mod test {
 
+    use async_compatibility_layer::art::async_spawn;
+    use async_std::{
+        future::timeout,
+        io::{ReadExt, WriteExt},
+        net::TcpStream,
+        sync::Barrier,
+        task::sleep,
+    };
     use committable::Committable;
     use espresso_types::{
-        v0_3::{RollupRegistration, RollupRegistrationBody, RollupUpdate, RollupUpdatebody},
-        SeqTypes,
+        v0_3::{
+            BidTx, BidTxBody, RollupRegistration, RollupRegistrationBody, RollupUpdate,
+            RollupUpdatebody,
+        },
+        EthKeyPair, FeeAmount, SeqTypes,
     };
+    use futures::{future::join_all, StreamExt};
     use hotshot::types::{BLSPubKey, SignatureKey};
-    use hotshot_types::traits::node_implementation::NodeType;
-    use std::str::FromStr;
-    use tide_disco::Url;
+    use hotshot_events_service::events_source::StartupInfo;
+    use hotshot_types::{
+        data::ViewNumber,
+        event::{Event, EventType},
+        signature_key::BLSPrivKey,
+        traits::node_implementation::{ConsensusTime, NodeType},
+    };
+    use std::{str::FromStr, sync::Arc, time::Duration};
+    use tide_disco::{metrics::Metrics, Error as _, StatusCode, Url};
+
+    use crate::{
+        admin::{AdminAction, AdminRequest, AdminRequestBody, AuditLogEntry, AuditLogQuery},
+        builders::BuilderRegistrationBody,
+        client::SolverClient,
+        feeds::RegistrationChangeKind,
+        mock::{generate_stake_table, staked_node_key, MockEvents, ReplayPace, STAKED_NODES},
+        recording::RecordedEvent,
+        registration_model,
+        shutdown::shutdown,
+        signing::SolverKey,
+        state::{GlobalState, SolverState, UpdateSolverState},
+        storage::{mock::setup_mock_storage, MemoryStorage, SolverStorage},
+        testing::{bid, registration, signed_bid, MockSolver},
+        ApiOptions, AuctionOptions, Liveness, RateLimitOptions, Readiness, SolverError,
+        SolverStatus,
+    };
 
-    use crate::{testing::MockSolver, SolverError};
+    /// Private key of the leader of `view_number` in the stake table of the mock events service.
+    fn leader_key(view_number: ViewNumber) -> BLSPrivKey {
+        staked_node_key(view_number.u64() % STAKED_NODES as u64)
+    }
 
     #[async_std::test]
     async fn test_rollup_registration() {
@@ -318,7 +504,7 @@ mod test {
             .unwrap_err();
 
         match err {
-            SolverError::Database(_) => {}
+            SolverError::RollupNotFound(namespace_id) if namespace_id == 1_u64.into() => {}
             _ => panic!("err {err:?}"),
         }
     }
@@ -477,16 +663,1233 @@ mod test {
             .unwrap_err();
     }
 
+    /// Client for the status and admin APIs, which the typed client does not cover
+    type ApiClient = surf_disco::Client<SolverError, <SeqTypes as NodeType>::Base>;
+
+    /// Registers a rollup with a reserve price of 200 and `http://reserve-{namespace_id}` as
+    /// reserve url.
+    async fn register_rollup(
+        client: &SolverClient,
+        namespace_id: u64,
+        active: bool,
+    ) -> RollupRegistration {
+        client
+            .register_rollup(&registration(namespace_id, 200, active))
+            .await
+            .unwrap()
+    }
+
+    async fn admin_action(
+        client: &ApiClient,
+        request: &AdminRequest,
+    ) -> Result<AuditLogEntry, SolverError> {
+        client
+            .post("action")
+            .body_json(request)
+            .unwrap()
+            .send()
+            .await
+    }
+
+    async fn audit_log(
+        client: &ApiClient,
+        private_key: &BLSPrivKey,
+    ) -> Result<Vec<AuditLogEntry>, SolverError> {
+        client
+            .post("audit_log")
+            .body_json(&AuditLogQuery::new().signed(private_key).unwrap())
+            .unwrap()
+            .send()
+            .await
+    }
+
+    async fn submit_bid(client: &SolverClient, bid: BidTx) -> Result<(), SolverError> {
+        client.submit_bid(&bid).await
+    }
+
+    /// How long a test waits for the solver to reach an expected state
+    const WAIT_TIMEOUT: Duration = Duration::from_secs(30);
+
+    /// A view far enough ahead of the last finalized view for its auction to stay open during
+    /// the test.
+    async fn open_view(mock_solver: &MockSolver) -> ViewNumber {
+        let finalized = mock_solver
+            .state()
+            .solver()
+            .finalized_view()
+            .map_or(0, |view| view.u64());
+
+        ViewNumber::new(finalized + 500)
+    }
+
     #[async_std::test]
-    async fn test_solver_api() {
+    async fn test_bundle_auction() {
         let mock_solver = MockSolver::init().await;
+        let solver_api = mock_solver.solver_api();
+        let client = SolverClient::new(solver_api);
+
+        // Register three rollups, each with a reserve price of 200
+        for namespace_id in 1..=3_u64 {
+            register_rollup(&client, namespace_id, true).await;
+        }
+
+        let view_number = open_view(&mock_solver).await;
+
+        // The bundle for namespaces 1 and 2 is worth more than both single slots together
+        let bundle = bid(view_number.u64(), &[1, 2], 1000);
+        let bids = vec![
+            bundle.clone(),
+            bid(view_number.u64(), &[1], 600),
+            bid(view_number.u64(), &[2], 300),
+        ];
 
+        for bid in bids {
+            submit_bid(&client, bid).await.unwrap();
+        }
+
+        // The auction is not finalized yet, so only the leader can see the results
+        client.auction_results(view_number).await.unwrap_err();
+        for key in [
+            // The leader of the next view
+            leader_key(ViewNumber::new(view_number.u64() + 1)),
+            <BLSPubKey as SignatureKey>::PrivateKey::generate(&mut rand::thread_rng()),
+        ] {
+            match client
+                .auction_results_permissioned(view_number, &key)
+                .await
+                .unwrap_err()
+            {
+                SolverError::Unauthorized(_) => {}
+                err => panic!("err {err:?}"),
+            }
+        }
+
+        let signed = client
+            .auction_results_permissioned(view_number, &leader_key(view_number))
+            .await
+            .unwrap();
+
+        // The results are signed by the solver, as results that may still change
+        let solver_key = client.solver_key().await.unwrap();
+        assert!(signed.provisional);
+        assert!(signed.verify(&solver_key));
+
+        let results = signed.results;
+        assert_eq!(results.winning_bids(), &[bundle]);
+        assert_eq!(
+            results.reserve_bids(),
+            &[(3_u64.into(), Url::from_str("http://reserve-3").unwrap())]
+        );
+    }
+
+    #[async_std::test]
+    async fn test_finalized_auction_outcome() {
+        let mock_solver = MockSolver::init().await;
         let solver_api = mock_solver.solver_api();
+        let client = SolverClient::new(solver_api);
 
-        let client =
-            surf_disco::Client::<SolverError, <SeqTypes as NodeType>::Base>::new(solver_api);
+        register_rollup(&client, 1, true).await;
+
+        let view_number = open_view(&mock_solver).await;
+        let bid = bid(view_number.u64(), &[1], 300);
+
+        submit_bid(&client, bid.clone()).await.unwrap();
+
+        let outcome = mock_solver
+            .state()
+            .finalize_auction(view_number)
+            .await
+            .unwrap();
+
+        // The published outcome contains the bid and can be recomputed offline
+        let published = client.auction_outcome(view_number).await.unwrap();
+
+        assert_eq!(published, outcome);
+        assert_eq!(published.inputs.bids, vec![bid.clone()]);
+        assert_eq!(published.results.winning_bids(), &[bid.clone()]);
+        published.verify().unwrap();
+
+        let signed = client.auction_results(view_number).await.unwrap();
+        assert_eq!(signed.results, outcome.results);
+        assert!(signed.verify(&mock_solver.state().solver_key()));
+
+        // Bidding for the finalized view is closed
+        let err = submit_bid(&client, bid).await.unwrap_err();
+
+        match err {
+            SolverError::BidViewOutOfRange { view, .. } if view == view_number.u64() => {}
+            _ => panic!("err {err:?}"),
+        }
+    }
+
+    #[async_std::test]
+    async fn test_bid_validation() {
+        let mock_solver = MockSolver::init().await;
+        let solver_api = mock_solver.solver_api();
+        let client = SolverClient::new(solver_api);
+
+        register_rollup(&client, 1, true).await;
+        register_rollup(&client, 2, false).await;
+
+        let view_number = open_view(&mock_solver).await;
+
+        // A valid bid is accepted
+        submit_bid(&client, bid(view_number.u64(), &[1], 300))
+            .await
+            .unwrap();
+
+        // The signature must come from the account in the bid
+        let key = EthKeyPair::random();
+        let forged = BidTxBody::new(
+            EthKeyPair::random().fee_account(),
+            FeeAmount::from(300),
+            view_number,
+            vec![1_u64.into()],
+            Url::from_str("http://builder").unwrap(),
+        )
+        .signed(&key)
+        .expect("failed to sign bid");
+        match submit_bid(&client, forged).await.unwrap_err() {
+            SolverError::InvalidBidSignature(_) => {}
+            err => panic!("err {err:?}"),
+        }
+
+        match submit_bid(&client, bid(view_number.u64(), &[], 300))
+            .await
+            .unwrap_err()
+        {
+            SolverError::EmptyBidNamespaces => {}
+            err => panic!("err {err:?}"),
+        }
+
+        match submit_bid(&client, bid(view_number.u64(), &[1, 1], 300))
+            .await
+            .unwrap_err()
+        {
+            SolverError::DuplicateBidNamespace(id) if id == 1_u64.into() => {}
+            err => panic!("err {err:?}"),
+        }
+
+        match submit_bid(&client, bid(view_number.u64(), &[1, 3], 300))
+            .await
+            .unwrap_err()
+        {
+            SolverError::UnregisteredNamespace(id) if id == 3_u64.into() => {}
+            err => panic!("err {err:?}"),
+        }
+
+        match submit_bid(&client, bid(view_number.u64(), &[2], 300))
+            .await
+            .unwrap_err()
+        {
+            SolverError::InactiveNamespace(id) if id == 2_u64.into() => {}
+            err => panic!("err {err:?}"),
+        }
+
+        let too_far = ViewNumber::new(view_number.u64() + 10_000);
+        match submit_bid(&client, bid(too_far.u64(), &[1], 300))
+            .await
+            .unwrap_err()
+        {
+            SolverError::BidViewOutOfRange { view, .. } if view == too_far.u64() => {}
+            err => panic!("err {err:?}"),
+        }
+
+        match submit_bid(&client, bid(view_number.u64(), &[1], 0))
+            .await
+            .unwrap_err()
+        {
+            SolverError::ZeroBidAmount => {}
+            err => panic!("err {err:?}"),
+        }
+
+        let key = EthKeyPair::random();
+        let bad_url = BidTxBody::new(
+            key.fee_account(),
+            FeeAmount::from(300),
+            view_number,
+            vec![1_u64.into()],
+            Url::from_str("ftp://builder").unwrap(),
+        )
+        .signed(&key)
+        .expect("failed to sign bid");
+        match submit_bid(&client, bad_url).await.unwrap_err() {
+            SolverError::InvalidBuilderUrl(_) => {}
+            err => panic!("err {err:?}"),
+        }
+
+        // Every rejection is counted by reason
+        let state = mock_solver.state();
+        let metrics = state.metrics();
+        assert_eq!(metrics.bids_received.get(), 9);
+        assert_eq!(metrics.bids_accepted.get(), 1);
+        for reason in [
+            "invalid_signature",
+            "empty_namespaces",
+            "duplicate_namespace",
+            "unregistered_namespace",
+            "inactive_namespace",
+            "view_out_of_range",
+            "zero_amount",
+            "invalid_builder_url",
+        ] {
+            assert_eq!(
+                metrics.bids_rejected.with_label_values(&[reason]).get(),
+                1,
+                "{reason}"
+            );
+        }
+
+        let exported = state.export_metrics().await.unwrap().export().unwrap();
+        assert!(exported.contains("marketplace_solver_registered_rollups 2"));
+        assert!(exported.contains("marketplace_solver_active_rollups 1"));
+    }
+
+    #[async_std::test]
+    async fn test_metrics_at_root() {
+        let mock_solver = MockSolver::init().await;
+        let client = ApiClient::new(mock_solver.status_api());
+        client.connect(None).await;
+
+        // Prometheus scrapes `/metrics` by default, outside the status API's prefix
+        let url = mock_solver.status_api();
+        let mut stream = TcpStream::connect((url.host_str().unwrap(), url.port().unwrap()))
+            .await
+            .unwrap();
+        stream
+            .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+        assert!(response.contains("marketplace_solver_bids_received"));
+    }
+
+    #[async_std::test]
+    async fn test_health_and_readiness() {
+        let mut mock_solver = MockSolver::init().await;
+        let client = ApiClient::new(mock_solver.status_api());
+        client.connect(None).await;
+
+        let liveness: Liveness = client.get("healthz").send().await.unwrap();
+        assert!(liveness.event_handler_running);
+
+        // Wait for the solver to subscribe and for the mock events service to produce a view
+        let readiness = timeout(WAIT_TIMEOUT, async {
+            loop {
+                if let Ok(readiness) = client.get::<Readiness>("readyz").send().await {
+                    if readiness.latest_view.is_some() {
+                        break readiness;
+                    }
+                }
+                sleep(Duration::from_millis(200)).await;
+            }
+        })
+        .await
+        .expect("solver never became ready");
+
+        assert!(readiness.database_reachable);
+        assert!(readiness.events_connected);
+
+        // Once the event handler stops, the solver is neither live nor ready
+        let event_handler = mock_solver.event_handler.take().unwrap();
+        event_handler.cancel().await;
+
+        client.get::<Liveness>("healthz").send().await.unwrap_err();
+        client.get::<Readiness>("readyz").send().await.unwrap_err();
+    }
+
+    #[async_std::test]
+    async fn test_listener_drains_requests() {
+        let storage = Arc::new(MemoryStorage::new());
+        let mock_solver =
+            MockSolver::init_with_storage(MockEvents::Manual, None, storage.clone()).await;
+        let client = SolverClient::new(mock_solver.solver_api());
+        client.connect(None).await;
+
+        // Hold a registration in flight
+        let barrier = Arc::new(Barrier::new(2));
+        storage.pause_next_operation(barrier.clone());
+        let registration = async_spawn({
+            let client = client.clone();
+            async move { register_rollup(&client, 1, true).await }
+        });
+        barrier.wait().await;
+
+        let listener = &mock_solver.listener;
+        listener.stop();
+        assert_eq!(listener.in_flight(), 1);
+        assert!(!listener.drain(Duration::from_millis(100)).await);
+
+        // The server no longer accepts connections
+        let url = mock_solver.solver_api();
+        let addr = (url.host_str().unwrap().to_string(), url.port().unwrap());
+        timeout(WAIT_TIMEOUT, async {
+            while TcpStream::connect(addr.clone()).await.is_ok() {
+                sleep(Duration::from_millis(100)).await;
+            }
+        })
+        .await
+        .expect("server kept accepting connections");
+
+        // But the request in flight is still answered
+        barrier.wait().await;
+        let registered = registration.await;
+        assert!(listener.drain(WAIT_TIMEOUT).await);
+        assert_eq!(
+            storage.get_rollup_registration(1_u64.into()).await.unwrap(),
+            Some(registered)
+        );
+    }
+
+    #[async_std::test]
+    async fn test_graceful_shutdown() {
+        let mut mock_solver = MockSolver::init().await;
+        let client = SolverClient::new(mock_solver.solver_api());
+        let state = mock_solver.state();
+
+        register_rollup(&client, 1, true).await;
+
+        // Wait for the mock events service to produce a view
+        let latest_view = timeout(WAIT_TIMEOUT, async {
+            loop {
+                if let Some(view) = state.events_health().latest_view() {
+                    break view;
+                }
+                sleep(Duration::from_millis(200)).await;
+            }
+        })
+        .await
+        .expect("no view was produced");
+
+        let event_handler = mock_solver.event_handler.take().unwrap();
+        shutdown(
+            state.clone(),
+            event_handler,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+
+        // The current view's auction was finalized before the database was closed
+        let finalized_view = state.solver().finalized_view().unwrap();
+        assert!(finalized_view.u64() >= latest_view);
+        assert!(state.storage().is_closed());
+        assert!(!state.events_health().running());
+
+        let view_number = ViewNumber::new(finalized_view.u64() + 1);
+        match submit_bid(&client, bid(view_number.u64(), &[1], 300))
+            .await
+            .unwrap_err()
+        {
+            SolverError::ShuttingDown => {}
+            err => panic!("err {err:?}"),
+        }
+    }
+
+    #[async_std::test]
+    async fn test_bids_per_builder_limit() {
+        let mock_solver = MockSolver::init().await;
+        let client = SolverClient::new(mock_solver.solver_api());
+
+        register_rollup(&client, 1, true).await;
+
+        let view_number = open_view(&mock_solver).await;
+        let max = mock_solver.state().auction_options().max_bids_per_builder;
+
+        let key = EthKeyPair::random();
+        let builder_bid = |amount: u64| {
+            BidTxBody::new(
+                key.fee_account(),
+                FeeAmount::from(amount),
+                view_number,
+                vec![1_u64.into()],
+                Url::from_str("http://builder").unwrap(),
+            )
+            .signed(&key)
+            .expect("failed to sign bid")
+        };
+
+        for amount in 1..=max as u64 {
+            submit_bid(&client, builder_bid(amount)).await.unwrap();
+        }
+
+        // Resubmitting a known bid is not counted against the limit
+        submit_bid(&client, builder_bid(1)).await.unwrap();
+
+        match submit_bid(&client, builder_bid(max as u64 + 1))
+            .await
+            .unwrap_err()
+        {
+            SolverError::TooManyBids { view, max: limit }
+                if view == view_number.u64() && limit == max => {}
+            err => panic!("err {err:?}"),
+        }
+
+        // Other builders can still bid for the view
+        submit_bid(&client, bid(view_number.u64(), &[1], 300))
+            .await
+            .unwrap();
+    }
+
+    #[async_std::test]
+    async fn test_request_limits() {
+        let (tmp_db, storage) = setup_mock_storage().await;
+        let api_options = ApiOptions {
+            limits: RateLimitOptions {
+                per_key_rate: 0.001,
+                per_key_burst: 2,
+                max_body_size: 4096,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mock_solver = MockSolver::init_with_options(
+            MockEvents::Manual,
+            tmp_db,
+            storage,
+            api_options,
+            Default::default(),
+        )
+        .await;
+        let client = SolverClient::new(mock_solver.solver_api());
+
+        register_rollup(&client, 1, true).await;
+        let view_number = open_view(&mock_solver).await;
+
+        let victim = EthKeyPair::random();
+        let attacker = EthKeyPair::random();
+        let victim_bid = |amount: u64, key: &EthKeyPair| {
+            BidTxBody::new(
+                victim.fee_account(),
+                FeeAmount::from(amount),
+                view_number,
+                vec![1_u64.into()],
+                Url::from_str("http://builder").unwrap(),
+            )
+            .signed(key)
+            .expect("failed to sign bid")
+        };
+
+        // Bids forged in the name of a builder do not use up its rate limit
+        for amount in 1..=5 {
+            match submit_bid(&client, victim_bid(amount, &attacker))
+                .await
+                .unwrap_err()
+            {
+                SolverError::InvalidBidSignature(_) => {}
+                err => panic!("err {err:?}"),
+            }
+        }
+
+        submit_bid(&client, victim_bid(1, &victim)).await.unwrap();
+        submit_bid(&client, victim_bid(2, &victim)).await.unwrap();
+        let err = submit_bid(&client, victim_bid(3, &victim))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, SolverError::RateLimited(_)), "err {err:?}");
+        assert_eq!(err.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        // A body over the limit is rejected before it is read
+        let key = EthKeyPair::random();
+        let large = BidTxBody::new(
+            key.fee_account(),
+            FeeAmount::from(300),
+            view_number,
+            vec![1_u64.into()],
+            Url::from_str(&format!("http://builder/{}", "a".repeat(8192))).unwrap(),
+        )
+        .signed(&key)
+        .expect("failed to sign bid");
+        let err = submit_bid(&client, large).await.unwrap_err();
+        assert_eq!(err.status(), StatusCode::PAYLOAD_TOO_LARGE, "err {err:?}");
+    }
+
+    #[async_std::test]
+    async fn test_builder_registration() {
+        let mock_solver = MockSolver::init().await;
+        let client = SolverClient::new(mock_solver.solver_api());
+
+        register_rollup(&client, 1, true).await;
+
+        let key = EthKeyPair::random();
+        let body = BuilderRegistrationBody {
+            account: key.fee_account(),
+            url: Url::from_str("http://builder").unwrap(),
+            text: "builder".to_string(),
+        };
+
+        // The registration must be signed by the registered account
+        let forged = body.clone().signed(&EthKeyPair::random()).unwrap();
+        match client.register_builder(&forged).await.unwrap_err() {
+            SolverError::InvalidSignature(_) => {}
+            err => panic!("err {err:?}"),
+        }
+
+        let registration = body.signed(&key).unwrap();
+        let result = client.register_builder(&registration).await.unwrap();
+        assert_eq!(result, registration);
+
+        let account = key.fee_account();
+        let fetched = client.builder_registration(account).await.unwrap();
+        assert_eq!(fetched, registration);
+
+        let all = client.builder_registrations().await.unwrap();
+        assert_eq!(all, vec![registration.clone()]);
+
+        // The metadata of the winning builders is published with the auction
+        let view_number = open_view(&mock_solver).await;
+        let winning_bid = BidTxBody::new(
+            account,
+            FeeAmount::from(1000),
+            view_number,
+            vec![1_u64.into()],
+            Url::from_str("http://builder").unwrap(),
+        )
+        .signed(&key)
+        .unwrap();
+        submit_bid(&client, winning_bid).await.unwrap();
+        submit_bid(&client, bid(view_number.u64(), &[1], 300))
+            .await
+            .unwrap();
+
+        mock_solver
+            .state()
+            .finalize_auction(view_number)
+            .await
+            .unwrap();
+
+        let builders = client.auction_builders(view_number).await.unwrap();
+        assert_eq!(builders, vec![registration]);
+    }
+
+    #[async_std::test]
+    async fn test_builder_access_lists() {
+        let allowed = EthKeyPair::random();
+        let denied = EthKeyPair::random();
+        let (tmp_db, storage) = setup_mock_storage().await;
+        let auction_options = AuctionOptions {
+            builder_allow_list: vec![allowed.fee_account(), denied.fee_account()],
+            builder_deny_list: vec![denied.fee_account()],
+            ..Default::default()
+        };
+        let mock_solver = MockSolver::init_with_options(
+            MockEvents::Manual,
+            tmp_db,
+            storage,
+            Default::default(),
+            auction_options,
+        )
+        .await;
+        let client = SolverClient::new(mock_solver.solver_api());
+
+        register_rollup(&client, 1, true).await;
+        let view_number = open_view(&mock_solver).await;
+
+        submit_bid(&client, signed_bid(&allowed, view_number.u64(), &[1], 300))
+            .await
+            .unwrap();
+
+        // Builders missing from the allow list may not bid
+        let other = EthKeyPair::random();
+        match submit_bid(&client, signed_bid(&other, view_number.u64(), &[1], 300))
+            .await
+            .unwrap_err()
+        {
+            SolverError::BuilderNotAllowed(account) if account == other.fee_account() => {}
+            err => panic!("err {err:?}"),
+        }
+
+        // The deny list takes precedence over the allow list, for bids and registrations
+        match submit_bid(&client, signed_bid(&denied, view_number.u64(), &[1], 300))
+            .await
+            .unwrap_err()
+        {
+            SolverError::BuilderNotAllowed(account) if account == denied.fee_account() => {}
+            err => panic!("err {err:?}"),
+        }
+
+        let registration = BuilderRegistrationBody {
+            account: denied.fee_account(),
+            url: Url::from_str("http://builder").unwrap(),
+            text: "builder".to_string(),
+        }
+        .signed(&denied)
+        .unwrap();
+        match client.register_builder(&registration).await.unwrap_err() {
+            SolverError::BuilderNotAllowed(account) if account == denied.fee_account() => {}
+            err => panic!("err {err:?}"),
+        }
+    }
+
+    #[async_std::test]
+    async fn test_require_builder_registration() {
+        let (tmp_db, storage) = setup_mock_storage().await;
+        let auction_options = AuctionOptions {
+            require_builder_registration: true,
+            ..Default::default()
+        };
+        let mock_solver = MockSolver::init_with_options(
+            MockEvents::Manual,
+            tmp_db,
+            storage,
+            Default::default(),
+            auction_options,
+        )
+        .await;
+        let client = SolverClient::new(mock_solver.solver_api());
+
+        register_rollup(&client, 1, true).await;
+        let view_number = open_view(&mock_solver).await;
+
+        let key = EthKeyPair::random();
+        match submit_bid(&client, signed_bid(&key, view_number.u64(), &[1], 300))
+            .await
+            .unwrap_err()
+        {
+            SolverError::UnregisteredBuilder(account) if account == key.fee_account() => {}
+            err => panic!("err {err:?}"),
+        }
+
+        let registration = BuilderRegistrationBody {
+            account: key.fee_account(),
+            url: Url::from_str("http://builder").unwrap(),
+            text: "builder".to_string(),
+        }
+        .signed(&key)
+        .unwrap();
+        client.register_builder(&registration).await.unwrap();
+
+        submit_bid(&client, signed_bid(&key, view_number.u64(), &[1], 300))
+            .await
+            .unwrap();
+    }
+
+    #[async_std::test]
+    async fn test_admin_api() {
+        let mock_solver = MockSolver::init().await;
+        let client = SolverClient::new(mock_solver.solver_api());
+        let admin = ApiClient::new(mock_solver.admin_api());
+        let operator_key = &mock_solver.operator_key;
+
+        register_rollup(&client, 1, true).await;
+        let view_number = open_view(&mock_solver).await;
+
+        // Only operator keys may sign admin requests
+        let other_key = <BLSPubKey as SignatureKey>::PrivateKey::generate(&mut rand::thread_rng());
+        let request = AdminRequestBody::new(AdminAction::PauseBidding)
+            .signed(&other_key)
+            .unwrap();
+        match admin_action(&admin, &request).await.unwrap_err() {
+            SolverError::Unauthorized(_) => {}
+            err => panic!("err {err:?}"),
+        }
+
+        let pause = AdminRequestBody::new(AdminAction::PauseBidding)
+            .signed(operator_key)
+            .unwrap();
+        admin_action(&admin, &pause).await.unwrap();
+
+        match submit_bid(&client, bid(view_number.u64(), &[1], 300))
+            .await
+            .unwrap_err()
+        {
+            SolverError::BiddingPaused => {}
+            err => panic!("err {err:?}"),
+        }
+
+        // The pause is persisted, so a restarted solver stays paused
+        let restarted = GlobalState::new(
+            mock_solver.storage.clone(),
+            SolverState::mock(),
+            Default::default(),
+            SolverKey::generate(),
+        )
+        .unwrap();
+        match restarted
+            .submit_bid_tx(bid(view_number.u64(), &[1], 300))
+            .await
+            .unwrap_err()
+        {
+            SolverError::BiddingPaused => {}
+            err => panic!("err {err:?}"),
+        }
+
+        // A signed request cannot be replayed
+        match admin_action(&admin, &pause).await.unwrap_err() {
+            SolverError::AdminRequestReplayed => {}
+            err => panic!("err {err:?}"),
+        }
+
+        let resume = AdminRequestBody::new(AdminAction::ResumeBidding)
+            .signed(operator_key)
+            .unwrap();
+        admin_action(&admin, &resume).await.unwrap();
+
+        let banned = bid(view_number.u64(), &[1], 300);
+        submit_bid(&client, banned.clone()).await.unwrap();
+
+        let ban = AdminRequestBody::new(AdminAction::BanBuilder(banned.account()))
+            .signed(operator_key)
+            .unwrap();
+        admin_action(&admin, &ban).await.unwrap();
+
+        match submit_bid(&client, banned.clone()).await.unwrap_err() {
+            SolverError::BuilderNotAllowed(account) if account == banned.account() => {}
+            err => panic!("err {err:?}"),
+        }
+
+        // The banned builder's pending bid no longer takes part in the auction
+        let outcome = mock_solver
+            .state()
+            .finalize_auction(view_number)
+            .await
+            .unwrap();
+        assert!(outcome.inputs.bids.is_empty());
+
+        let delete = AdminRequestBody::new(AdminAction::DeleteRollup(1_u64.into()))
+            .signed(operator_key)
+            .unwrap();
+        admin_action(&admin, &delete).await.unwrap();
+
+        let registrations = client.rollup_registrations().await.unwrap();
+        assert!(registrations.is_empty());
+
+        let delete_missing = AdminRequestBody::new(AdminAction::DeleteRollup(2_u64.into()))
+            .signed(operator_key)
+            .unwrap();
+        match admin_action(&admin, &delete_missing).await.unwrap_err() {
+            SolverError::RollupNotFound(id) if id == 2_u64.into() => {}
+            err => panic!("err {err:?}"),
+        }
+
+        // Only operators may read the audit log
+        match audit_log(&admin, &other_key).await.unwrap_err() {
+            SolverError::Unauthorized(_) => {}
+            err => panic!("err {err:?}"),
+        }
+
+        // Every executed action is audited, including the one that failed
+        let log = audit_log(&admin, operator_key).await.unwrap();
+        let actions: Vec<_> = log.iter().map(|entry| entry.action.clone()).collect();
+        assert_eq!(
+            actions,
+            vec![
+                AdminAction::PauseBidding,
+                AdminAction::ResumeBidding,
+                AdminAction::BanBuilder(banned.account()),
+                AdminAction::DeleteRollup(1_u64.into()),
+                AdminAction::DeleteRollup(2_u64.into()),
+            ]
+        );
+        assert!(log[..4].iter().all(|entry| entry.error.is_none()));
+        assert!(log[4].error.is_some());
+        let operator = BLSPubKey::from_private(operator_key).to_string();
+        assert!(log.iter().all(|entry| entry.operator == operator));
+    }
+
+    #[async_std::test]
+    async fn test_auction_results_stream() {
+        let mock_solver = MockSolver::init().await;
+        let client = SolverClient::new(mock_solver.solver_api());
+        client.connect(None).await;
+
+        register_rollup(&client, 1, true).await;
+
+        let mut auctions = client.subscribe_auction_results(true).await.unwrap();
+
+        let view_number = open_view(&mock_solver).await;
+        let bids = vec![
+            bid(view_number.u64(), &[1], 300),
+            bid(view_number.u64(), &[1], 400),
+        ];
+        for bid in &bids {
+            submit_bid(&client, bid.clone()).await.unwrap();
+        }
+
+        let outcome = mock_solver
+            .state()
+            .finalize_auction(view_number)
+            .await
+            .unwrap();
+
+        // The mock events service finalizes other views in the meantime
+        let auction = loop {
+            let auction = auctions.next().await.unwrap().unwrap();
+            if auction.view_number == view_number.u64() {
+                break auction;
+            }
+        };
+
+        assert_eq!(auction.results.results, outcome.results);
+        assert_eq!(auction.results.results.winning_bids(), &[bids[1].clone()]);
+        assert_eq!(auction.bid_count, Some(2));
+
+        let solver_key = client.solver_key().await.unwrap();
+        assert!(auction.results.verify(&solver_key));
+    }
+
+    #[async_std::test]
+    async fn test_registration_changes() {
+        let mock_solver = MockSolver::init().await;
+        let client = SolverClient::new(mock_solver.solver_api());
+        client.connect(None).await;
+
+        let mut live = client.subscribe_registration_changes(None).await.unwrap();
+
+        let first = register_rollup(&client, 1, true).await;
+        let change = live.next().await.unwrap().unwrap();
+        assert_eq!(change.namespace_id, 1_u64.into());
+        assert_eq!(change.kind, RegistrationChangeKind::Registered);
+        assert_eq!(change.registration, Some(first));
+
+        let second = register_rollup(&client, 2, true).await;
+
+        let delete = AdminRequestBody::new(AdminAction::DeleteRollup(1_u64.into()))
+            .signed(&mock_solver.operator_key)
+            .unwrap();
+        admin_action(&ApiClient::new(mock_solver.admin_api()), &delete)
+            .await
+            .unwrap();
+
+        // A client that saw only the first change resumes right after it
+        let mut resumed = client
+            .subscribe_registration_changes(Some(change.cursor))
+            .await
+            .unwrap();
+
+        let registered = resumed.next().await.unwrap().unwrap();
+        assert_eq!(registered.kind, RegistrationChangeKind::Registered);
+        assert_eq!(registered.registration, Some(second));
+
+        let deleted = resumed.next().await.unwrap().unwrap();
+        assert_eq!(deleted.namespace_id, 1_u64.into());
+        assert_eq!(deleted.kind, RegistrationChangeKind::Deleted);
+        assert_eq!(deleted.registration, None);
+        assert!(change.cursor < registered.cursor && registered.cursor < deleted.cursor);
+
+        // Changes made after subscribing follow the replayed ones
+        let third = register_rollup(&client, 3, true).await;
+        let change = resumed.next().await.unwrap().unwrap();
+        assert_eq!(change.registration, Some(third));
+    }
+
+    #[async_std::test]
+    async fn test_replayed_events_finalize_exact_views() {
+        let events = (1..=5)
+            .map(|view| RecordedEvent {
+                offset: Duration::ZERO,
+                event: Event {
+                    view_number: ViewNumber::new(view + 1),
+                    event: EventType::ViewFinished {
+                        view_number: ViewNumber::new(view),
+                    },
+                },
+            })
+            .collect();
+
+        let (steps, step_receiver) = async_std::channel::unbounded();
+        let mock_solver = MockSolver::init_with_events(MockEvents::Replay {
+            startup_info: StartupInfo {
+                known_node_with_stake: generate_stake_table(),
+                non_staked_node_count: 10,
+            },
+            events,
+            pace: ReplayPace::Step(step_receiver),
+        })
+        .await;
+        let client = SolverClient::new(mock_solver.solver_api());
+        client.connect(None).await;
+
+        register_rollup(&client, 1, true).await;
+        let bid = bid(3, &[1], 300);
+        submit_bid(&client, bid.clone()).await.unwrap();
+
+        // Events are only delivered to subscribers, so wait for the solver to subscribe
+        let health = mock_solver.state().events_health();
+        timeout(WAIT_TIMEOUT, async {
+            while !health.connected() {
+                sleep(Duration::from_millis(100)).await;
+            }
+        })
+        .await
+        .expect("solver did not subscribe to events");
+
+        for _ in 1..=3 {
+            steps.send(()).await.unwrap();
+        }
+
+        timeout(WAIT_TIMEOUT, async {
+            while mock_solver.state().solver().finalized_view() != Some(ViewNumber::new(3)) {
+                sleep(Duration::from_millis(100)).await;
+            }
+        })
+        .await
+        .expect("view 3 was not finalized");
+
+        let outcome = client.auction_outcome(ViewNumber::new(3)).await.unwrap();
+        assert_eq!(outcome.results.winning_bids(), &[bid]);
+
+        // Nothing past the last replayed event is finalized
+        client
+            .auction_outcome(ViewNumber::new(4))
+            .await
+            .unwrap_err();
+        assert_eq!(health.latest_view(), Some(4));
+    }
+
+    #[async_std::test]
+    async fn test_controlled_events_outage() {
+        let mock_solver = MockSolver::init_with_events(MockEvents::Manual).await;
+        let client = SolverClient::new(mock_solver.solver_api());
+        client.connect(None).await;
+
+        let health = mock_solver.state().events_health();
+        let wait_until = |connected: bool| {
+            let health = health.clone();
+            async move {
+                timeout(WAIT_TIMEOUT, async {
+                    while health.connected() != connected {
+                        sleep(Duration::from_millis(100)).await;
+                    }
+                })
+                .await
+                .unwrap_or_else(|_| panic!("connected never became {connected}"));
+            }
+        };
+        wait_until(true).await;
+
+        register_rollup(&client, 1, true).await;
+        let bid = bid(5, &[1], 300);
+        submit_bid(&client, bid.clone()).await.unwrap();
+
+        // Nothing is finalized until the test finishes a view
+        mock_solver.events.finish_view(ViewNumber::new(5)).await;
+        timeout(WAIT_TIMEOUT, async {
+            while mock_solver.state().solver().finalized_view() != Some(ViewNumber::new(5)) {
+                sleep(Duration::from_millis(100)).await;
+            }
+        })
+        .await
+        .expect("view 5 was not finalized");
+        let outcome = client.auction_outcome(ViewNumber::new(5)).await.unwrap();
+        assert_eq!(outcome.results.winning_bids(), &[bid]);
+
+        // The solver notices the outage and resubscribes once the service is back
+        mock_solver.events.disconnect().await;
+        wait_until(false).await;
+
+        mock_solver.events.reconnect().await;
+        wait_until(true).await;
+        assert!(mock_solver.state().metrics().event_stream_reconnects.get() >= 1);
+    }
+
+    #[async_std::test]
+    async fn test_bidding_opens_from_current_view() {
+        let mock_solver = MockSolver::init_with_events(MockEvents::Manual).await;
+        let client = SolverClient::new(mock_solver.solver_api());
+        client.connect(None).await;
+
+        let state = mock_solver.state();
+        let health = state.events_health();
+        timeout(WAIT_TIMEOUT, async {
+            while !health.connected() {
+                sleep(Duration::from_millis(100)).await;
+            }
+        })
+        .await
+        .expect("solver did not subscribe to events");
+
+        register_rollup(&client, 1, true).await;
+
+        // The network is far past the views open before the solver hears from it
+        let current = ViewNumber::new(5000);
+        match submit_bid(&client, bid(current.u64(), &[1], 300))
+            .await
+            .unwrap_err()
+        {
+            SolverError::BidViewOutOfRange { view, .. } if view == current.u64() => {}
+            err => panic!("err {err:?}"),
+        }
+
+        // Any event tells the solver the current view, without waiting for a view to finish
+        mock_solver
+            .events
+            .push(
+                current,
+                EventType::ViewTimeout {
+                    view_number: ViewNumber::new(4999),
+                },
+            )
+            .await;
+        timeout(WAIT_TIMEOUT, async {
+            while health.latest_view() != Some(current.u64()) {
+                sleep(Duration::from_millis(100)).await;
+            }
+        })
+        .await
+        .expect("solver did not receive the event");
+
+        submit_bid(&client, bid(current.u64(), &[1], 300))
+            .await
+            .unwrap();
+        submit_bid(&client, bid(5010, &[1], 300)).await.unwrap();
+
+        let past = 4999;
+        match submit_bid(&client, bid(past, &[1], 300)).await.unwrap_err() {
+            SolverError::BidViewOutOfRange { view, first, .. }
+                if view == past && first == current.u64() => {}
+            err => panic!("err {err:?}"),
+        }
+    }
+
+    #[async_std::test]
+    async fn test_bid_amount_limit() {
+        let (tmp_db, storage) = setup_mock_storage().await;
+        let auction_options = AuctionOptions {
+            max_bid_amount: Some(FeeAmount::from(1000)),
+            ..Default::default()
+        };
+        let mock_solver = MockSolver::init_with_options(
+            MockEvents::Manual,
+            tmp_db,
+            storage,
+            Default::default(),
+            auction_options,
+        )
+        .await;
+        let client = SolverClient::new(mock_solver.solver_api());
+
+        register_rollup(&client, 1, true).await;
+        let view_number = open_view(&mock_solver).await;
+
+        submit_bid(&client, bid(view_number.u64(), &[1], 1000))
+            .await
+            .unwrap();
+        match submit_bid(&client, bid(view_number.u64(), &[1], 1001))
+            .await
+            .unwrap_err()
+        {
+            SolverError::BidAmountTooLarge { amount, max } => {
+                assert_eq!(amount, FeeAmount::from(1001).to_string());
+                assert_eq!(max, FeeAmount::from(1000).to_string());
+            }
+            err => panic!("err {err:?}"),
+        }
+    }
+
+    #[async_std::test]
+    async fn test_bids_restored_after_restart() {
+        let mock_solver = MockSolver::init_with_events(MockEvents::Manual).await;
+        let client = SolverClient::new(mock_solver.solver_api());
+        client.connect(None).await;
+
+        register_rollup(&client, 1, true).await;
+        let view_number = open_view(&mock_solver).await;
+        let bids = vec![
+            bid(view_number.u64(), &[1], 300),
+            bid(view_number.u64() + 1, &[1], 400),
+        ];
+        for bid in &bids {
+            submit_bid(&client, bid.clone()).await.unwrap();
+        }
+
+        // A solver restarted over the same storage picks up the open auctions
+        let restarted = GlobalState::new(
+            mock_solver.storage.clone(),
+            SolverState::mock(),
+            Default::default(),
+            SolverKey::generate(),
+        )
+        .unwrap();
+        restarted.open_bidding_from(view_number).await.unwrap();
+        assert_eq!(restarted.solver().bid_txs.bids(view_number), bids[..1]);
+
+        // Stored bids are deleted once their auction is finalized
+        restarted.finalize_auction(view_number).await.unwrap();
+        assert!(mock_solver
+            .storage
+            .get_bids(view_number)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            mock_solver
+                .storage
+                .get_bids_from(view_number)
+                .await
+                .unwrap(),
+            bids[1..]
+        );
+    }
+
+    #[async_std::test]
+    async fn test_bids_not_blocked_by_storage_writes() {
+        let storage = Arc::new(MemoryStorage::new());
+        let mock_solver =
+            MockSolver::init_with_storage(MockEvents::Manual, None, storage.clone()).await;
+        let client = SolverClient::new(mock_solver.solver_api());
+
+        register_rollup(&client, 1, true).await;
+        let view_number = open_view(&mock_solver).await;
+
+        // The first bid loads the registrations and bans into memory
+        submit_bid(&client, bid(view_number.u64(), &[1], 100))
+            .await
+            .unwrap();
+
+        // Hold the storage write of a registration until the bids are in
+        let barrier = Arc::new(Barrier::new(2));
+        storage.pause_next_operation(barrier.clone());
+        let registration = async_spawn({
+            let client = client.clone();
+            async move { register_rollup(&client, 2, true).await }
+        });
+        barrier.wait().await;
+
+        // Under a lock held for the whole registration, the bids would wait for the write forever
+        let results = timeout(
+            WAIT_TIMEOUT,
+            join_all((0..20).map(|_| submit_bid(&client, bid(view_number.u64(), &[1], 200)))),
+        )
+        .await
+        .expect("bids blocked by the storage write");
+        for result in results {
+            result.unwrap();
+        }
+
+        barrier.wait().await;
+        registration.await;
+        let registrations = client.rollup_registrations().await.unwrap();
+        assert_eq!(registrations.len(), 2);
+    }
+
+    #[test]
+    fn test_registrations_over_api() {
+        let (tmp_db, storage) = async_std::task::block_on(setup_mock_storage());
+        // Every sequence is signed by the same few keys
+        let api_options = ApiOptions {
+            limits: RateLimitOptions {
+                per_key_rate: 1e9,
+                per_key_burst: u32::MAX,
+                per_ip_rate: 1e9,
+                per_ip_burst: u32::MAX,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mock_solver = async_std::task::block_on(MockSolver::init_with_options(
+            MockEvents::Manual,
+            tmp_db,
+            storage,
+            api_options,
+            Default::default(),
+        ));
+        let client = SolverClient::new(mock_solver.solver_api());
+        async_std::task::block_on(client.connect(None));
 
-        let result: String = client.post("submit_bid").send().await.unwrap();
-        assert_eq!(result, "Bid Submitted".to_string());
+        registration_model::run(&client, 16, |_, _| {});
     }
 }
@@ -0,0 +1,204 @@
+//! Synthetic auction traffic for benchmarks and load tests.
+//!
+//! A workload is a set of rollups, each registered with its own key, and a set of builders that
+//! bid on random bundles of their namespaces. Bundles and amounts are drawn from a seeded RNG, so
+//! the same options produce bid books of the same shape; the builder and rollup keys are fresh
+//! every run.
+use std::str::FromStr;
+
+use clap::Parser;
+use espresso_types::{
+    v0_3::{BidTx, BidTxBody, RollupRegistration, RollupRegistrationBody},
+    EthKeyPair, FeeAmount, NamespaceId,
+};
+use hotshot::types::{BLSPubKey, SignatureKey};
+use hotshot_types::{
+    data::ViewNumber, signature_key::BLSPrivKey, traits::node_implementation::ConsensusTime,
+};
+use rand::{rngs::StdRng, seq::index, Rng, SeedableRng};
+use tide_disco::Url;
+
+use crate::{auction::AuctionInputs, client::sign_registration};
+
+/// Reserve price of every rollup in a workload
+pub const RESERVE_PRICE: u64 = 100;
+
+/// Arguments describing the shape of synthetic auction traffic
+#[derive(Clone, Debug, Parser)]
+pub struct WorkloadOptions {
+    /// Number of builders bidding in every view
+    #[clap(long, default_value_t = 20)]
+    pub builders: usize,
+
+    /// Number of registered rollups, at least one for the builders to bid on
+    #[clap(long, default_value_t = 10, value_parser = clap::value_parser!(u64).range(1..))]
+    pub namespaces: u64,
+
+    /// Bids each builder submits per view
+    #[clap(long, default_value_t = 4)]
+    pub bids_per_builder: usize,
+
+    /// Maximum number of namespaces a single bid covers
+    #[clap(long, default_value_t = 3)]
+    pub max_bundle_size: usize,
+
+    /// Seed of the bundles and amounts
+    #[clap(long, default_value_t = 0)]
+    pub seed: u64,
+}
+
+impl Default for WorkloadOptions {
+    fn default() -> Self {
+        Self {
+            builders: 20,
+            namespaces: 10,
+            bids_per_builder: 4,
+            max_bundle_size: 3,
+            seed: 0,
+        }
+    }
+}
+
+pub struct Workload {
+    options: WorkloadOptions,
+    builders: Vec<EthKeyPair>,
+    rng: StdRng,
+}
+
+impl Workload {
+    pub fn new(options: WorkloadOptions) -> Self {
+        let builders = (0..options.builders)
+            .map(|_| EthKeyPair::random())
+            .collect();
+        let rng = StdRng::seed_from_u64(options.seed);
+
+        Self {
+            options,
+            builders,
+            rng,
+        }
+    }
+
+    pub fn options(&self) -> &WorkloadOptions {
+        &self.options
+    }
+
+    /// Active registrations of the rollups the builders bid on, each signed with its own key.
+    pub fn registrations(&self) -> Vec<RollupRegistration> {
+        (1..=self.options.namespaces)
+            .map(|namespace_id| self.registration(namespace_id))
+            .collect()
+    }
+
+    /// An active registration of `namespace_id`, signed with a new key.
+    ///
+    /// Keys do not come from the seeded RNG, so registering more rollups leaves the bids the same.
+    pub fn registration(&self, namespace_id: u64) -> RollupRegistration {
+        let private_key = BLSPrivKey::generate(&mut rand::thread_rng());
+        let signature_key = BLSPubKey::from_private(&private_key);
+
+        let body = RollupRegistrationBody {
+            namespace_id: namespace_id.into(),
+            reserve_url: Url::from_str(&format!("http://reserve-{namespace_id}")).unwrap(),
+            reserve_price: RESERVE_PRICE.into(),
+            active: true,
+            signature_keys: vec![signature_key],
+            text: format!("rollup {namespace_id}"),
+            signature_key,
+        };
+
+        sign_registration(body, &private_key).expect("failed to sign registration")
+    }
+
+    /// The bids of every builder for `view`.
+    ///
+    /// Each bid covers a random bundle of distinct namespaces and, on average, pays well above
+    /// their combined reserve price, so most bids are eligible.
+    pub fn bids(&mut self, view: u64) -> Vec<BidTx> {
+        let namespaces = self.options.namespaces as usize;
+        let max_bundle_size = self.options.max_bundle_size.clamp(1, namespaces.max(1));
+
+        let mut bids = Vec::with_capacity(self.builders.len() * self.options.bids_per_builder);
+        for (i, builder) in self.builders.iter().enumerate() {
+            for _ in 0..self.options.bids_per_builder {
+                let size = self.rng.gen_range(1..=max_bundle_size);
+                let bundle: Vec<NamespaceId> = index::sample(&mut self.rng, namespaces, size)
+                    .into_iter()
+                    .map(|i| (i as u64 + 1).into())
+                    .collect();
+                let amount =
+                    size as u64 * self.rng.gen_range(RESERVE_PRICE / 2..=10 * RESERVE_PRICE);
+
+                let bid = BidTxBody::new(
+                    builder.fee_account(),
+                    FeeAmount::from(amount),
+                    ViewNumber::new(view),
+                    bundle,
+                    Url::from_str(&format!("http://builder-{i}")).unwrap(),
+                )
+                .signed(builder)
+                .expect("failed to sign bid");
+                bids.push(bid);
+            }
+        }
+
+        bids
+    }
+
+    /// The inputs of the auction for `view`, as the solver would collect them.
+    pub fn auction_inputs(&mut self, view: u64) -> AuctionInputs {
+        let registrations = self.registrations();
+        let bids = self.bids(view);
+
+        AuctionInputs::new(ViewNumber::new(view), bids, registrations)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use clap::Parser;
+
+    use super::{Workload, WorkloadOptions};
+
+    #[test]
+    fn test_workload() {
+        let options = WorkloadOptions {
+            builders: 3,
+            namespaces: 5,
+            bids_per_builder: 2,
+            max_bundle_size: 4,
+            seed: 7,
+        };
+        let mut workload = Workload::new(options);
+
+        let registrations = workload.registrations();
+        assert_eq!(registrations.len(), 5);
+
+        let bids = workload.bids(1);
+        assert_eq!(bids.len(), 6);
+        for bid in &bids {
+            let namespaces = bid.namespaces();
+            let distinct: HashSet<_> = namespaces.iter().collect();
+            assert!(!namespaces.is_empty() && namespaces.len() <= 4);
+            assert_eq!(distinct.len(), namespaces.len());
+            assert!(namespaces
+                .iter()
+                .all(|ns| (1..=5).contains(&u64::from(*ns))));
+            bid.verify().unwrap();
+        }
+
+        let inputs = workload.auction_inputs(2);
+        assert_eq!(inputs.bids.len(), 6);
+        assert_eq!(inputs.registrations.len(), 5);
+    }
+
+    #[test]
+    fn test_workload_options_require_namespaces() {
+        assert!(WorkloadOptions::try_parse_from(["workload", "--namespaces", "0"]).is_err());
+
+        let options = WorkloadOptions::try_parse_from(["workload", "--namespaces", "1"]).unwrap();
+        assert_eq!(options.namespaces, 1);
+    }
+}